@@ -1,27 +1,103 @@
-use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use std::collections::BTreeMap;
 use std::io;
-use std::thread;
-use std::time::Duration;
-use crate::types::{DetailItem, DetailSelection};
-use crate::ui;
+use std::time::{Duration, Instant};
+
+use crate::clipboard::{Clipboard, SystemClipboard};
+use crate::editor::Editor;
+use crate::events::{AppEvent, EventQueue, TICK_RATE};
+use crate::highlight::Highlighter;
+use crate::history::History;
+use crate::input::translate_key;
+use crate::keymap::Keymap;
+use crate::markup;
+use crate::palette::Palette;
+use crate::prompt::{PromptAction, PromptForm};
+use crate::search::{SearchField, SearchState};
+use crate::output::{self, RecordingSink, SinkKind};
+use crate::storage::{Storage, StoredData, SCHEMA_VERSION};
+use crate::template::{self, Context, SystemContext};
+use crate::text_view::TextView;
+use crate::typing::{TypingJob, TypingOutcome, TypingOverrides, TypingSettings, TypingUpdate};
+use crate::types::{AppState, DetailItem, DetailSelection, InputEvent, Subscription};
 use crate::utils;
-use ratatui::Terminal;
-use ratatui::backend::CrosstermBackend;
+use crossterm::event::KeyEventKind;
 use ratatui::widgets::ListState;
 
+/// 数据修改前的快照，用于撤销列表操作和保存
+pub struct AppSnapshot {
+    items: Vec<Subscription>,
+    selected: Option<usize>,
+    detail_selected: Option<usize>,
+    current_subscription: usize,
+    current_detail_index: usize,
+}
+
+/// 提示消息显示的时间
+const TOAST_DURATION: Duration = Duration::from_secs(2);
+
+/// 短暂显示在界面右上角的提示消息
+pub struct Toast {
+    pub message: String,
+    shown_at: Instant,
+}
+
+/// 全屏帮助的状态
+pub struct HelpOverlay {
+    /// 向下滚动的行数，绘制时会限制在内容范围内
+    pub scroll: usize,
+    /// 一页可见的行数，绘制时更新，用于翻页
+    pub page: usize,
+    /// 关闭帮助后返回的视图
+    pub return_state: AppState,
+}
+
 pub struct App {
-    pub items: Vec<String>,
+    pub items: Vec<Subscription>,
     pub state: ListState,
     pub is_pinned: bool,
-    pub detail_items: Vec<DetailItem>,
-    pub in_detail_view: bool,
-    pub in_detail_page: bool,
+    pub app_state: AppState,
     pub detail_state: ListState,
+    pub current_subscription: usize,
     pub current_detail_index: usize,
     pub current_detail_selection: DetailSelection,
-    pub in_edit_mode: bool,
-    pub in_save_mode: bool,
-    pub edit_buffer: String,
+    pub editor: Editor,
+    pub history: History<AppSnapshot>,
+    pub storage: Option<Storage>,
+    pub keymap: Keymap,
+    pub status_message: Option<String>,
+    /// 模拟键盘输出使用的输出目标
+    pub output_sink: SinkKind,
+    /// 输出目标为 recorder 时记录按键动作
+    pub recorder: RecordingSink,
+    /// 全局的输出节奏，详情项可以单独覆盖
+    pub typing_settings: TypingSettings,
+    /// 正在进行的模拟键盘输出任务，同一时间只有一个
+    pub typing: Option<TypingJob>,
+    /// 输出前填写模板变量的表单
+    pub prompt: Option<PromptForm>,
+    /// 内置模板占位符使用的时钟、随机数等数据来源
+    pub template_context: Box<dyn Context>,
+    /// 详情页面中显示的展开预览：详情项位置和展开后的文本
+    pub preview: Option<((usize, usize), String)>,
+    /// 复制文本使用的剪贴板
+    pub clipboard: Box<dyn Clipboard>,
+    pub toast: Option<Toast>,
+    /// 搜索界面的状态
+    pub search: Option<SearchState>,
+    /// 命令面板的状态
+    pub palette: Option<Palette>,
+    /// 全屏帮助的状态
+    pub help: Option<HelpOverlay>,
+    /// 主循环的事件队列，后台任务的消息也发送到这里
+    pub events: EventQueue,
+    /// 详情页面文本区块的滚动位置和显示选项
+    pub text_view: TextView,
+    /// 详情项文本的语法高亮，在后台线程中计算并缓存
+    pub highlighter: Highlighter,
+    /// 数据每次修改（写回存储）时加一，用于判断缓存的结果是否过期
+    revision: u64,
+    /// 详情页面文本的检查结果，和检查时的文本来源一起保存，来源不变时不重新检查
+    text_error: Option<(TextSource, Option<String>)>,
 }
 
 impl App {
@@ -32,57 +108,271 @@ impl App {
         let mut detail_state = ListState::default();
         detail_state.select(Some(0));
         
-        let detail_items = vec![
-            DetailItem {
-                title: "学习Rust".to_string(),
-                describe: "掌握Rust编程语言的基础知识".to_string(),
-                text: "println!(\"Hello, Rust!\");".to_string(),
-            },
-            DetailItem {
-                title: "构建TUI应用".to_string(),
-                describe: "使用Ratatui库创建终端用户界面".to_string(),
-                text: "let app = App::new();".to_string(),
-            },
-            DetailItem {
-                title: "探索Ratatui".to_string(),
-                describe: "深入了解Ratatui的各种组件和功能".to_string(),
-                text: "terminal.draw(|f| ui(f, &mut app))?;".to_string(),
-            },
+        let events = EventQueue::new(TICK_RATE);
+        let highlighter = Highlighter::new(events.sender());
+
+        let sample_details = [
+            DetailItem::new("学习Rust", "掌握Rust编程语言的基础知识", "println!(\"Hello, Rust!\");"),
+            DetailItem::new("构建TUI应用", "使用Ratatui库创建终端用户界面", "let app = App::new();"),
+            DetailItem::new("探索Ratatui", "深入了解Ratatui的各种组件和功能", "terminal.draw(|f| ui(f, &mut app))?;"),
         ];
         
+        let names = [
+            "Item 1: Learn Rust",
+            "Item 2: Build TUI applications",
+            "Item 3: Explore Ratatui",
+            "Item 4: Create smart lists",
+            "Item 5: Handle user input",
+            "Item 6: Navigate with keyboard",
+            "Item 7: Style with colors",
+            "Item 8: Manage state",
+            "Item 9: Build interactive UIs",
+            "Item 10: Deploy applications",
+            "Item 11: Test with various inputs",
+            "Item 12: Document the code",
+            "Item 13: Share with community",
+            "Item 14: Get feedback",
+            "Item 15: Improve based on feedback",
+        ];
+
+        // 前三个示例订阅各带一个示例详情项
+        let items = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| Subscription {
+                name: name.to_string(),
+                details: sample_details.get(i).cloned().into_iter().collect(),
+            })
+            .collect();
+
         App {
-            items: vec![
-                "Item 1: Learn Rust".to_string(),
-                "Item 2: Build TUI applications".to_string(),
-                "Item 3: Explore Ratatui".to_string(),
-                "Item 4: Create smart lists".to_string(),
-                "Item 5: Handle user input".to_string(),
-                "Item 6: Navigate with keyboard".to_string(),
-                "Item 7: Style with colors".to_string(),
-                "Item 8: Manage state".to_string(),
-                "Item 9: Build interactive UIs".to_string(),
-                "Item 10: Deploy applications".to_string(),
-                "Item 11: Test with various inputs".to_string(),
-                "Item 12: Document the code".to_string(),
-                "Item 13: Share with community".to_string(),
-                "Item 14: Get feedback".to_string(),
-                "Item 15: Improve based on feedback".to_string(),
-            ],
+            items,
             state,
             is_pinned: false,
-            detail_items,
-            in_detail_view: false,
-            in_detail_page: false,
+            app_state: AppState::MainView,
             detail_state,
+            current_subscription: 0,
             current_detail_index: 0,
             current_detail_selection: DetailSelection::Title,
-            in_edit_mode: false,
-            in_save_mode: false,
-            edit_buffer: String::new(),
+            editor: Editor::default(),
+            history: History::default(),
+            storage: None,
+            keymap: Keymap::default(),
+            status_message: None,
+            output_sink: SinkKind::default(),
+            recorder: RecordingSink::default(),
+            typing_settings: TypingSettings::default(),
+            typing: None,
+            prompt: None,
+            template_context: Box::new(SystemContext),
+            preview: None,
+            clipboard: Box::new(SystemClipboard),
+            toast: None,
+            search: None,
+            palette: None,
+            help: None,
+            events,
+            text_view: TextView::default(),
+            highlighter,
+            revision: 0,
+            text_error: None,
+        }
+    }
+
+    /// 从存储中加载数据创建应用实例，数据文件不存在时使用示例数据
+    pub fn with_storage(storage: Storage) -> io::Result<App> {
+        let mut app = App::new();
+        if let Some(data) = storage.load()? {
+            app.items = data.subscriptions;
+            if app.items.is_empty() {
+                app.state.select(None);
+            }
+        }
+        app.storage = Some(storage);
+        Ok(app)
+    }
+
+    /// 将当前数据写回存储，失败时在状态栏显示错误
+    pub fn persist(&mut self) {
+        self.revision += 1;
+        let Some(storage) = &self.storage else {
+            return;
+        };
+
+        let data = StoredData {
+            version: SCHEMA_VERSION,
+            subscriptions: self.items.clone(),
+        };
+        if let Err(e) = storage.save(&data) {
+            self.status_message = Some(format!("保存失败: {}", e));
+        }
+    }
+
+    /// 当前打开的订阅的详情项列表
+    pub fn details(&self) -> &[DetailItem] {
+        self.items
+            .get(self.current_subscription)
+            .map(|s| s.details.as_slice())
+            .unwrap_or(&[])
+    }
+
+    pub fn details_mut(&mut self) -> Option<&mut Vec<DetailItem>> {
+        self.items
+            .get_mut(self.current_subscription)
+            .map(|s| &mut s.details)
+    }
+
+    /// 详情页面中正在查看的详情项
+    pub fn current_detail(&self) -> Option<&DetailItem> {
+        self.details().get(self.current_detail_index)
+    }
+
+    pub fn current_detail_mut(&mut self) -> Option<&mut DetailItem> {
+        let index = self.current_detail_index;
+        self.details_mut().and_then(|d| d.get_mut(index))
+    }
+
+    /// 打开主列表中选中的订阅
+    pub fn open_selected_subscription(&mut self) -> bool {
+        let Some(selected) = self.state.selected() else {
+            return false;
+        };
+        if selected >= self.items.len() {
+            return false;
+        }
+
+        self.current_subscription = selected;
+        self.current_detail_index = 0;
+        let first = if self.details().is_empty() { None } else { Some(0) };
+        self.detail_state.select(first);
+        true
+    }
+
+    /// 在主列表末尾添加新的订阅
+    pub fn add_subscription(&mut self) -> bool {
+        self.items.push(Subscription::new(format!("新订阅项 {}", self.items.len() + 1)));
+        true
+    }
+
+    /// 删除主列表中选中的订阅
+    pub fn delete_selected_subscription(&mut self) -> bool {
+        if self.items.is_empty() {
+            return false;
+        }
+
+        let selected = self.state.selected().unwrap_or(0);
+        self.items.remove(selected);
+        self.state.select(clamp_selection(selected, self.items.len()));
+        true
+    }
+
+    /// 按名称排序订阅
+    pub fn sort_subscriptions(&mut self) -> bool {
+        self.items.sort_by(|a, b| a.name.cmp(&b.name));
+        true
+    }
+
+    /// 在当前订阅中添加新的详情项
+    pub fn add_detail(&mut self) -> bool {
+        let Some(details) = self.details_mut() else {
+            return false;
+        };
+
+        details.push(DetailItem::new(format!("新详情项 {}", details.len() + 1), "", ""));
+        let last = details.len() - 1;
+        self.detail_state.select(Some(last));
+        true
+    }
+
+    /// 删除当前订阅中选中的详情项
+    pub fn delete_selected_detail(&mut self) -> bool {
+        let Some(selected) = self.detail_state.selected() else {
+            return false;
+        };
+        let Some(details) = self.details_mut() else {
+            return false;
+        };
+        if selected >= details.len() {
+            return false;
+        }
+
+        details.remove(selected);
+        let len = details.len();
+        self.detail_state.select(clamp_selection(selected, len));
+        true
+    }
+
+    /// 执行一个修改数据的操作，发生修改时记录撤销历史并写回存储
+    pub fn modify(&mut self, operation: impl FnOnce(&mut App) -> bool) {
+        let before = self.snapshot();
+        if operation(self) {
+            self.history.record(before);
+            self.persist();
+        }
+    }
+
+    /// 撤销上一步数据修改
+    pub fn undo(&mut self) {
+        match self.history.undo(self.snapshot()) {
+            Some(snapshot) => {
+                self.restore(snapshot);
+                self.persist();
+            }
+            None => self.status_message = Some("没有可撤销的操作".to_string()),
+        }
+    }
+
+    /// 重做上一步撤销的修改
+    pub fn redo(&mut self) {
+        match self.history.redo(self.snapshot()) {
+            Some(snapshot) => {
+                self.restore(snapshot);
+                self.persist();
+            }
+            None => self.status_message = Some("没有可重做的操作".to_string()),
+        }
+    }
+
+    fn snapshot(&self) -> AppSnapshot {
+        AppSnapshot {
+            items: self.items.clone(),
+            selected: self.state.selected(),
+            detail_selected: self.detail_state.selected(),
+            current_subscription: self.current_subscription,
+            current_detail_index: self.current_detail_index,
+        }
+    }
+
+    /// 恢复快照，并在当前视图对应的数据已不存在时退回上一级视图
+    fn restore(&mut self, snapshot: AppSnapshot) {
+        // 撤销的修改发生在其他详情项，或增删了当前订阅的详情项时，详情页面原来显示的详情项
+        // 可能已经不存在或换了位置，此时返回详情视图，避免不知不觉显示另一个详情项
+        let page_moved = self.app_state == AppState::DetailPage
+            && ((snapshot.current_subscription, snapshot.current_detail_index)
+                != (self.current_subscription, self.current_detail_index)
+                || snapshot.items.get(self.current_subscription).map(|s| s.details.len())
+                    != Some(self.details().len()));
+
+        self.items = snapshot.items;
+        self.state.select(snapshot.selected.and_then(|i| clamp_selection(i, self.items.len())));
+        self.current_subscription = snapshot.current_subscription;
+        self.current_detail_index = snapshot.current_detail_index;
+
+        if self.current_subscription >= self.items.len() {
+            self.app_state = AppState::MainView;
+        } else if self.app_state == AppState::DetailPage && (page_moved || self.current_detail().is_none()) {
+            self.app_state = AppState::DetailView;
+            self.status_message = Some("正在查看的详情项已变化，已返回详情列表".to_string());
         }
+        let details_len = self.details().len();
+        self.detail_state.select(snapshot.detail_selected.and_then(|i| clamp_selection(i, details_len)));
     }
 
     pub fn next(&mut self) {
+        if self.items.is_empty() {
+            return;
+        }
+
         let i = match self.state.selected() {
             Some(i) => {
                 if i >= self.items.len() - 1 {
@@ -97,6 +387,10 @@ impl App {
     }
 
     pub fn previous(&mut self) {
+        if self.items.is_empty() {
+            return;
+        }
+
         let i = match self.state.selected() {
             Some(i) => {
                 if i == 0 {
@@ -111,13 +405,14 @@ impl App {
     }
 
     pub fn next_detail(&mut self) {
-        if self.detail_items.is_empty() {
+        let len = self.details().len();
+        if len == 0 {
             return;
         }
         
         let i = match self.detail_state.selected() {
             Some(i) => {
-                if i >= self.detail_items.len() - 1 {
+                if i >= len - 1 {
                     0
                 } else {
                     i + 1
@@ -129,14 +424,15 @@ impl App {
     }
 
     pub fn previous_detail(&mut self) {
-        if self.detail_items.is_empty() {
+        let len = self.details().len();
+        if len == 0 {
             return;
         }
         
         let i = match self.detail_state.selected() {
             Some(i) => {
                 if i == 0 {
-                    self.detail_items.len() - 1
+                    len - 1
                 } else {
                     i - 1
                 }
@@ -151,346 +447,1463 @@ impl App {
         utils::set_window_topmost(self.is_pinned);
     }
 
-    pub fn simulate_keyboard_output(&self, text: &str) {
-        utils::simulate_keyboard_output(text);
+    /// 把选中区域的内容载入编辑器，成功时返回 true
+    pub fn load_editor(&mut self) -> bool {
+        let Some(text) = self.selected_field_text() else {
+            return false;
+        };
+
+        self.editor = Editor::new(&text);
+        true
     }
 
-    pub fn handle_key_event(&mut self, key_code: KeyCode) {
-        match key_code {
-            KeyCode::Char('q') => {
-                if self.in_save_mode {
-                    self.in_save_mode = false;
-                } else if self.in_edit_mode {
-                    self.edit_buffer.push('q');
-                } else if self.in_detail_page {
-                    self.in_detail_page = false;
-                } else if self.in_detail_view {
-                    self.in_detail_view = false;
-                } else {
-                    // 程序将在这里结束
+    /// 详情页面中选中区域的文本，输出设置以 TOML 文本表示
+    pub fn selected_field_text(&self) -> Option<String> {
+        let item = self.current_detail()?;
+        Some(match self.current_detail_selection {
+            DetailSelection::Title => item.title.clone(),
+            DetailSelection::Language => item.language.clone().unwrap_or_default(),
+            DetailSelection::Describe => item.describe.clone(),
+            DetailSelection::Text => item.text.clone(),
+            DetailSelection::Typing => item.typing.to_text(self.typing_settings),
+        })
+    }
+
+    /// 检查编辑器中的文本能否写回选中区域
+    fn validate_editor(&self) -> Result<(), String> {
+        match self.current_detail_selection {
+            DetailSelection::Typing => TypingOverrides::parse(&self.editor.text()).map(|_| ()),
+            DetailSelection::Language if self.editor.text().trim().contains(char::is_whitespace) => {
+                Err("语言名称不能包含空格或换行".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// 把编辑器中的文本写回选中区域，并清空编辑器
+    pub fn store_editor(&mut self) {
+        let buffer = std::mem::take(&mut self.editor).text();
+        let selection = self.current_detail_selection;
+        if let Some(item) = self.current_detail_mut() {
+            match selection {
+                DetailSelection::Title => item.title = buffer,
+                DetailSelection::Language => {
+                    let language = buffer.trim();
+                    item.language = (!language.is_empty()).then(|| language.to_string());
+                }
+                DetailSelection::Describe => item.describe = buffer,
+                DetailSelection::Text => item.text = buffer,
+                DetailSelection::Typing => {
+                    if let Ok(typing) = TypingOverrides::parse(&buffer) {
+                        item.typing = typing;
+                    }
                 }
             }
-            KeyCode::Esc => {
-                if self.in_edit_mode {
-                    self.in_edit_mode = false;
-                    self.in_save_mode = true;
-                } else if self.in_save_mode {
-                    self.in_save_mode = false;
-                    self.in_edit_mode = true;
-                } else if self.in_detail_page {
-                    self.in_detail_page = false;
-                } else if self.in_detail_view {
-                    self.in_detail_view = false;
-                } else {
-                    // 程序将在这里结束
+        }
+    }
+
+    /// 清空详情页面中选中区域的内容
+    pub fn clear_selected_field(&mut self) -> bool {
+        let selection = self.current_detail_selection;
+        let Some(item) = self.current_detail_mut() else {
+            return false;
+        };
+
+        match selection {
+            DetailSelection::Title => item.title.clear(),
+            DetailSelection::Language => item.language = None,
+            DetailSelection::Describe => item.describe.clear(),
+            DetailSelection::Text => item.text.clear(),
+            DetailSelection::Typing => item.typing = TypingOverrides::default(),
+        }
+        true
+    }
+
+    /// 详情页面或详情视图中选中的详情项位置（订阅序号, 详情项序号），用于模拟键盘输出
+    pub fn output_location(&self) -> Option<(usize, usize)> {
+        let index = match self.app_state {
+            AppState::DetailPage => self.current_detail_index,
+            AppState::DetailView => self.detail_state.selected()?,
+            _ => return None,
+        };
+        (index < self.details().len()).then_some((self.current_subscription, index))
+    }
+
+    fn detail_at(&self, (subscription, detail): (usize, usize)) -> Option<&DetailItem> {
+        self.items.get(subscription)?.details.get(detail)
+    }
+
+    /// 主视图 -> 详情视图：打开选中的订阅
+    pub fn enter_detail_view(&mut self) {
+        if self.app_state == AppState::MainView && self.open_selected_subscription() {
+            self.app_state = AppState::DetailView;
+        }
+    }
+
+    /// 详情视图 -> 详情页面：查看选中的详情项
+    pub fn enter_detail_page(&mut self) {
+        if self.app_state != AppState::DetailView {
+            return;
+        }
+        if let Some(selected) = self.detail_state.selected()
+            && selected < self.details().len()
+        {
+            self.current_detail_index = selected;
+            self.app_state = AppState::DetailPage;
+        }
+    }
+
+    /// 详情页面 -> 编辑模式：把选中区域载入编辑器
+    pub fn start_editing(&mut self) {
+        if self.app_state == AppState::DetailPage && self.load_editor() {
+            // 编辑后预览不再准确
+            self.preview = None;
+            self.app_state = AppState::EditMode;
+        }
+    }
+
+    /// 编辑模式 -> 保存模式
+    pub fn finish_editing(&mut self) {
+        if self.app_state == AppState::EditMode {
+            self.app_state = AppState::SaveMode;
+        }
+    }
+
+    /// 保存模式 -> 编辑模式
+    pub fn resume_editing(&mut self) {
+        if self.app_state == AppState::SaveMode {
+            self.app_state = AppState::EditMode;
+        }
+    }
+
+    /// 保存模式 -> 详情页面：保存编辑内容并写回存储
+    pub fn confirm_save(&mut self) {
+        if self.app_state == AppState::SaveMode {
+            // 内容无效时回到编辑模式继续修改
+            if let Err(e) = self.validate_editor() {
+                self.status_message = Some(e);
+                self.app_state = AppState::EditMode;
+                return;
+            }
+            self.app_state = AppState::DetailPage;
+            self.modify(|app| {
+                app.store_editor();
+                true
+            });
+        }
+    }
+
+    /// 保存模式 -> 详情页面：放弃编辑内容
+    pub fn discard_edit(&mut self) {
+        if self.app_state == AppState::SaveMode {
+            self.editor = Editor::default();
+            self.app_state = AppState::DetailPage;
+        }
+    }
+
+    /// 返回上一级视图，在主视图中返回 false 表示退出程序
+    pub fn go_back(&mut self) -> bool {
+        self.app_state = match self.app_state {
+            AppState::MainView => return false,
+            AppState::DetailView => AppState::MainView,
+            AppState::DetailPage => AppState::DetailView,
+            AppState::EditMode => AppState::SaveMode,
+            AppState::SaveMode => AppState::EditMode,
+            AppState::PromptMode => {
+                self.cancel_prompt();
+                return true;
+            }
+            AppState::SearchMode => {
+                self.cancel_search();
+                return true;
+            }
+            AppState::PaletteMode => {
+                self.close_palette();
+                return true;
+            }
+            AppState::HelpMode => {
+                self.close_help();
+                return true;
+            }
+        };
+        true
+    }
+
+    /// 打开命令面板，列出当前视图的全部动作
+    fn open_palette(&mut self) {
+        self.palette = Some(Palette::new(&self.keymap, self.app_state));
+        self.app_state = AppState::PaletteMode;
+    }
+
+    /// 命令面板 -> 原来的视图
+    fn close_palette(&mut self) {
+        if let Some(palette) = self.palette.take() {
+            self.app_state = palette.return_state;
+        }
+    }
+
+    /// 关闭命令面板，在原来的视图中执行选中的动作，返回 false 表示退出程序
+    fn run_palette_action(&mut self) -> bool {
+        let event = self.palette.as_ref().and_then(|p| p.selected_event());
+        self.close_palette();
+        match event {
+            Some(event) => self.apply(event),
+            None => true,
+        }
+    }
+
+    /// 打开全屏帮助，列出所有视图的按键
+    fn show_help(&mut self) {
+        self.help = Some(HelpOverlay {
+            scroll: 0,
+            page: 1,
+            return_state: self.app_state,
+        });
+        self.app_state = AppState::HelpMode;
+    }
+
+    /// 帮助 -> 原来的视图
+    fn close_help(&mut self) {
+        if let Some(help) = self.help.take() {
+            self.app_state = help.return_state;
+        }
+    }
+
+    /// 浏览视图 -> 搜索
+    fn start_search(&mut self) {
+        self.search = Some(SearchState::new(self.app_state));
+        self.app_state = AppState::SearchMode;
+    }
+
+    /// 搜索 -> 原来的视图
+    fn cancel_search(&mut self) {
+        if let Some(search) = self.search.take() {
+            self.app_state = search.return_state;
+        }
+    }
+
+    /// 搜索 -> 详情页面或详情视图：打开选中的结果，匹配到的字段会被选中
+    fn open_search_hit(&mut self) {
+        let Some(hit) = self.search.as_ref().and_then(|s| s.selected_hit()) else {
+            return;
+        };
+        let (subscription, detail, field) = (hit.subscription, hit.detail, hit.field);
+        self.search = None;
+
+        self.state.select(Some(subscription));
+        self.open_selected_subscription();
+        self.app_state = AppState::DetailView;
+        if let Some(detail) = detail {
+            self.detail_state.select(Some(detail));
+            self.current_detail_index = detail;
+            self.current_detail_selection = match field {
+                SearchField::Describe => DetailSelection::Describe,
+                SearchField::Text => DetailSelection::Text,
+                _ => DetailSelection::Title,
+            };
+            self.app_state = AppState::DetailPage;
+        }
+    }
+
+    /// 切换详情页面中选中的区域
+    fn next_selection(&mut self) {
+        self.current_detail_selection = match self.current_detail_selection {
+            DetailSelection::Title => DetailSelection::Language,
+            DetailSelection::Language => DetailSelection::Describe,
+            DetailSelection::Describe => DetailSelection::Text,
+            DetailSelection::Text => DetailSelection::Typing,
+            DetailSelection::Typing => DetailSelection::Title,
+        };
+    }
+
+    fn previous_selection(&mut self) {
+        self.current_detail_selection = match self.current_detail_selection {
+            DetailSelection::Title => DetailSelection::Typing,
+            DetailSelection::Language => DetailSelection::Title,
+            DetailSelection::Describe => DetailSelection::Language,
+            DetailSelection::Text => DetailSelection::Describe,
+            DetailSelection::Typing => DetailSelection::Text,
+        };
+    }
+
+    /// 详情页面中切换到下一个详情项
+    fn next_detail_page(&mut self) {
+        let len = self.details().len();
+        if len > 0 {
+            self.current_detail_index = (self.current_detail_index + 1) % len;
+            self.detail_state.select(Some(self.current_detail_index));
+        }
+    }
+
+    /// 模拟键盘输出或复制选中的详情项，文本中有模板变量时先打开表单填写
+    fn start_output(&mut self, action: PromptAction) {
+        let Some(location) = self.output_location() else {
+            return;
+        };
+        if action == PromptAction::Type && self.typing.is_some() {
+            self.status_message = Some("已有正在进行的输出任务，请等待完成或先取消".to_string());
+            return;
+        }
+
+        let variables = match template::parse_item(&self.items, location) {
+            Ok(segments) => template::variables(&segments),
+            Err(e) => {
+                self.status_message = Some(e);
+                return;
+            }
+        };
+
+        if variables.is_empty() {
+            self.finish_output(action, location, &BTreeMap::new());
+        } else {
+            let item = &self.items[location.0].details[location.1];
+            self.prompt = Some(PromptForm::new(
+                variables,
+                &item.variables,
+                location,
+                self.app_state,
+                action,
+            ));
+            self.app_state = AppState::PromptMode;
+        }
+    }
+
+    /// 填写变量 -> 原来的视图：记住填写的值并开始输出或复制
+    fn submit_prompt(&mut self) {
+        let Some(form) = self.prompt.take() else {
+            return;
+        };
+        self.app_state = form.return_state;
+
+        let location = (form.subscription, form.detail);
+        let values = form.values();
+        if let Some(item) = self.items.get_mut(location.0).and_then(|s| s.details.get_mut(location.1)) {
+            item.variables = values.clone();
+        }
+        self.persist();
+        self.finish_output(form.action, location, &values);
+    }
+
+    fn finish_output(&mut self, action: PromptAction, location: (usize, usize), values: &BTreeMap<String, String>) {
+        match action {
+            PromptAction::Type => self.begin_typing(location, values),
+            PromptAction::Copy => self.copy_item(location, values),
+        }
+    }
+
+    /// 填写变量 -> 原来的视图：放弃输出
+    fn cancel_prompt(&mut self) {
+        if let Some(form) = self.prompt.take() {
+            self.app_state = form.return_state;
+        }
+    }
+
+    /// 展开详情项的引用和模板，返回带按键标记的文本以及文本中是否使用了 `${counter}`
+    fn expand_item(
+        &mut self,
+        location: (usize, usize),
+        values: &BTreeMap<String, String>,
+    ) -> Result<(String, bool), String> {
+        let segments = template::parse_item(&self.items, location)?;
+        let counter = self.items[location.0].details[location.1].counter + 1;
+        let text = template::expand(&segments, values, counter, self.template_context.as_mut())
+            .map_err(|e| format!("模板展开失败: {}", e))?;
+        Ok((text, template::uses_counter(&segments)))
+    }
+
+    /// 把展开后的文本复制到剪贴板，组合键和等待等标记不会被复制
+    fn copy_item(&mut self, location: (usize, usize), values: &BTreeMap<String, String>) {
+        let (text, uses_counter) = match self.expand_item(location, values) {
+            Ok(expanded) => expanded,
+            Err(e) => {
+                self.status_message = Some(e);
+                return;
+            }
+        };
+        let text = match markup::parse(&text) {
+            Ok(actions) => output::plain_text(&actions),
+            Err(e) => {
+                self.status_message = Some(format!("文本标记错误，{}", e));
+                return;
+            }
+        };
+
+        match self.clipboard.copy(&text) {
+            Ok(method) => {
+                self.show_toast(format!("已复制 {} 个字符到剪贴板 ({})", text.chars().count(), method));
+                if uses_counter {
+                    self.items[location.0].details[location.1].counter += 1;
+                    self.persist();
                 }
             }
-            KeyCode::Down | KeyCode::Char('j') => {
-                if self.in_detail_page {
-                    match self.current_detail_selection {
-                        DetailSelection::Title => self.current_detail_selection = DetailSelection::Describe,
-                        DetailSelection::Describe => self.current_detail_selection = DetailSelection::Text,
-                        DetailSelection::Text => self.current_detail_selection = DetailSelection::Title,
-                    }
-                } else if self.in_detail_view {
-                    self.next_detail();
-                } else {
-                    self.next();
+            Err(e) => self.status_message = Some(format!("复制到剪贴板失败: {}", e)),
+        }
+    }
+
+    pub fn show_toast(&mut self, message: String) {
+        self.toast = Some(Toast {
+            message,
+            shown_at: Instant::now(),
+        });
+    }
+
+    pub fn current_toast(&self) -> Option<&str> {
+        self.toast.as_ref().map(|toast| toast.message.as_str())
+    }
+
+    /// 提示消息超过显示时间后移除，在 Tick 事件中调用
+    fn expire_toast(&mut self) {
+        if self.toast.as_ref().is_some_and(|toast| toast.shown_at.elapsed() >= TOAST_DURATION) {
+            self.toast = None;
+        }
+    }
+
+    /// 展开模板和按键标记，在后台开始输出
+    fn begin_typing(&mut self, location: (usize, usize), values: &BTreeMap<String, String>) {
+        let Some(item) = self.detail_at(location) else {
+            return;
+        };
+        let settings = item.typing.resolve(self.typing_settings);
+        let (text, uses_counter) = match self.expand_item(location, values) {
+            Ok(expanded) => expanded,
+            Err(e) => {
+                self.status_message = Some(e);
+                return;
+            }
+        };
+        let actions = match markup::parse(&text) {
+            Ok(actions) => actions,
+            Err(e) => {
+                self.status_message = Some(format!("文本标记错误，{}", e));
+                return;
+            }
+        };
+
+        // 有动作不能输出时整段都不输出，不会只输入一部分
+        let sink = output::open_sink(self.output_sink, &self.recorder).and_then(|sink| {
+            sink.check(&actions)?;
+            Ok(sink)
+        });
+        match sink {
+            Ok(sink) => {
+                self.typing = Some(TypingJob::start(sink, actions, settings, self.events.sender()));
+                // 只有真正开始输出时才消耗一个序号
+                if uses_counter {
+                    self.items[location.0].details[location.1].counter += 1;
+                    self.persist();
                 }
-            },
-            KeyCode::Up | KeyCode::Char('k') => {
-                if self.in_detail_page {
-                    match self.current_detail_selection {
-                        DetailSelection::Title => self.current_detail_selection = DetailSelection::Text,
-                        DetailSelection::Describe => self.current_detail_selection = DetailSelection::Title,
-                        DetailSelection::Text => self.current_detail_selection = DetailSelection::Describe,
-                    }
-                } else if self.in_detail_view {
-                    self.previous_detail();
-                } else {
-                    self.previous();
+            }
+            Err(e) => self.status_message = Some(format!("无法模拟键盘输出: {}", e)),
+        }
+    }
+
+    /// 详情页面：显示或关闭选中详情项展开后的文本预览
+    ///
+    /// 变量使用上次填写的值或默认值，预览不会消耗 `${counter}` 的序号。
+    fn toggle_preview(&mut self) {
+        if self.preview.take().is_some() {
+            return;
+        }
+        let location = (self.current_subscription, self.current_detail_index);
+        let Some(item) = self.detail_at(location) else {
+            return;
+        };
+        let values = item.variables.clone();
+        match self.expand_item(location, &values) {
+            Ok((text, _)) => self.preview = Some((location, text)),
+            Err(e) => self.status_message = Some(e),
+        }
+    }
+
+    /// 当前详情项的文本预览，切换到其他详情项后不再显示
+    pub fn current_preview(&self) -> Option<&str> {
+        match &self.preview {
+            Some((location, text)) if *location == (self.current_subscription, self.current_detail_index) => {
+                Some(text)
+            }
+            _ => None,
+        }
+    }
+
+    /// 请求取消正在进行的输出任务
+    fn cancel_typing(&mut self) {
+        match &self.typing {
+            Some(job) => job.cancel(),
+            None => self.status_message = Some("没有正在进行的输出任务".to_string()),
+        }
+    }
+
+    /// 处理输出任务的进度消息，任务结束时在状态栏显示结果
+    fn update_typing(&mut self, update: TypingUpdate) {
+        let Some(job) = &mut self.typing else {
+            return;
+        };
+        let Some(outcome) = job.update(update) else {
+            return;
+        };
+
+        self.typing = None;
+        self.status_message = Some(match outcome {
+            TypingOutcome::Completed => "输出完成".to_string(),
+            TypingOutcome::Cancelled => "输出已取消".to_string(),
+            TypingOutcome::Failed(e) => format!("模拟键盘输出失败: {}", e),
+        });
+    }
+
+    /// 处理事件队列中的一个事件，返回 false 表示退出程序
+    ///
+    /// 每个事件处理后主循环都会重新绘制，窗口大小变化只需要重新绘制。
+    pub fn handle_event(&mut self, event: AppEvent) -> bool {
+        match event {
+            AppEvent::Key(key) if key.kind == KeyEventKind::Press => {
+                let input = translate_key(&self.keymap, self.app_state, key);
+                let source = self.text_source();
+                if !self.apply(input) {
+                    return false;
                 }
-            },
-            KeyCode::Char('a') => {
-                self.items.push(format!("新订阅项 {}", self.items.len() + 1));
-            },
-            KeyCode::Char('d') => {
-                if !self.items.is_empty() {
-                    let selected = self.state.selected().unwrap_or(0);
-                    self.items.remove(selected);
-                    
-                    if self.items.is_empty() {
-                        self.state.select(None);
-                    } else {
-                        let new_selected = if selected >= self.items.len() {
-                            self.items.len() - 1
-                        } else {
-                            selected
-                        };
-                        self.state.select(Some(new_selected));
-                    }
+                // 只有文本、语言或打开的详情项变化时才需要重新高亮
+                if self.text_source() != source {
+                    self.request_highlight();
                 }
-            },
-            KeyCode::Char('r') => {
-                self.items.sort();
-            },
-            KeyCode::Char('t') => {
-                self.toggle_pin();
-            },
-            KeyCode::Enter => {
-                if self.in_edit_mode {
-                    self.edit_buffer.push('\n');
-                } else {
-                    if !self.in_detail_view {
-                        self.in_detail_view = true;
-                        if !self.detail_items.is_empty() {
-                            self.detail_state.select(Some(0));
-                        }
-                    } else if !self.in_detail_page {
-                        if let Some(selected) = self.detail_state.selected() {
-                            self.in_detail_page = true;
-                            self.current_detail_index = selected;
-                        }
+            }
+            AppEvent::Key(_) | AppEvent::Resize => {}
+            AppEvent::Tick => self.expire_toast(),
+            AppEvent::Typing(update) => self.update_typing(update),
+            AppEvent::Highlighted(result) => self.highlighter.store(result),
+        }
+        true
+    }
+
+    /// 详情页面文本中的错误，编辑中只检查编辑器或文本中的标记，否则还检查引用的其他详情项
+    ///
+    /// 每次绘制都会调用，文本没有编辑、数据没有修改时直接返回上次的结果。
+    pub fn text_error(&mut self) -> Option<String> {
+        let source = self.text_source();
+        if let Some((cached, error)) = &self.text_error
+            && *cached == source
+        {
+            return error.clone();
+        }
+
+        let location = (self.current_subscription, self.current_detail_index);
+        let item = self.current_detail()?;
+        let editing = matches!(self.app_state, AppState::EditMode | AppState::SaveMode);
+        let error = if !editing && let Err(e) = template::parse_item(&self.items, location) {
+            Some(e)
+        } else {
+            let checked = if editing && self.current_detail_selection == DetailSelection::Text {
+                template::validate(&self.editor.text())
+            } else {
+                template::validate(&item.text)
+            };
+            checked.err().map(|e| format!("标记错误: {}", e))
+        };
+        self.text_error = Some((source, error.clone()));
+        error
+    }
+
+    /// 决定详情页面文本区块内容的状态：视图、详情项、选中区域、编辑器和数据的版本、是否显示预览
+    fn text_source(&self) -> TextSource {
+        (
+            self.app_state,
+            (self.current_subscription, self.current_detail_index),
+            self.current_detail_selection,
+            self.editor.revision(),
+            self.revision,
+            self.preview.is_some(),
+        )
+    }
+
+    /// 详情页面中文本区块显示的内容，在后台计算高亮，不在绘制时计算
+    pub fn request_highlight(&mut self) {
+        if !matches!(self.app_state, AppState::DetailPage | AppState::EditMode) {
+            return;
+        }
+        let Some(item) = self.current_detail() else {
+            return;
+        };
+        let text = if self.app_state == AppState::EditMode && self.current_detail_selection == DetailSelection::Text {
+            self.editor.text()
+        } else {
+            self.current_preview().unwrap_or(&item.text).to_string()
+        };
+        let language = item.language.clone();
+        let location = (self.current_subscription, self.current_detail_index);
+        self.highlighter.request(location, language.as_deref(), &text);
+    }
+
+    /// 按当前状态处理输入事件，返回 false 表示退出程序
+    pub fn apply(&mut self, event: InputEvent) -> bool {
+        self.status_message = None;
+
+        match (self.app_state, event) {
+            // 浮层之间不叠加，只能在普通视图中打开命令面板和全屏帮助
+            (state, InputEvent::Help | InputEvent::ShowHelp) if is_overlay(state) => {}
+            (_, InputEvent::Help) => self.open_palette(),
+            (_, InputEvent::ShowHelp) => self.show_help(),
+
+            // 全屏帮助：上下滚动，Esc 关闭
+            (AppState::HelpMode, InputEvent::Escape) => self.close_help(),
+            (AppState::HelpMode, event) => {
+                if let Some(help) = &mut self.help {
+                    match event {
+                        InputEvent::Up => help.scroll = help.scroll.saturating_sub(1),
+                        InputEvent::Down => help.scroll = help.scroll.saturating_add(1),
+                        InputEvent::PageUp => help.scroll = help.scroll.saturating_sub(help.page),
+                        InputEvent::PageDown => help.scroll = help.scroll.saturating_add(help.page),
+                        InputEvent::Home => help.scroll = 0,
+                        InputEvent::End => help.scroll = usize::MAX,
+                        _ => {}
                     }
                 }
             }
-            KeyCode::Char('v') => {
-                if self.in_detail_page && !self.in_edit_mode && !self.in_save_mode {
-                    if self.current_detail_index < self.detail_items.len() {
-                        let current_item = &self.detail_items[self.current_detail_index];
-                        self.edit_buffer = match self.current_detail_selection {
-                            DetailSelection::Title => current_item.title.clone(),
-                            DetailSelection::Describe => current_item.describe.clone(),
-                            DetailSelection::Text => current_item.text.clone(),
-                        };
-                        self.in_edit_mode = true;
-                    }
+
+            // 编辑模式：Esc 结束编辑，方向键移动光标，其余按键作为文本输入
+            (AppState::EditMode, InputEvent::Escape) => self.finish_editing(),
+            (AppState::EditMode, InputEvent::Enter) => self.editor.insert_newline(),
+            (AppState::EditMode, InputEvent::Backspace) => self.editor.backspace(),
+            (AppState::EditMode, InputEvent::Delete) => self.editor.delete(),
+            (AppState::EditMode, InputEvent::Up) => self.editor.move_up(),
+            (AppState::EditMode, InputEvent::Down) => self.editor.move_down(),
+            (AppState::EditMode, InputEvent::Left) => self.editor.move_left(),
+            (AppState::EditMode, InputEvent::Right) => self.editor.move_right(),
+            (AppState::EditMode, InputEvent::Home) => self.editor.move_home(),
+            (AppState::EditMode, InputEvent::End) => self.editor.move_end(),
+            (AppState::EditMode, InputEvent::WordLeft) => self.editor.move_word_left(),
+            (AppState::EditMode, InputEvent::WordRight) => self.editor.move_word_right(),
+            (AppState::EditMode, InputEvent::PageUp) => {
+                for _ in 0..self.text_view.page {
+                    self.editor.move_up();
                 }
-            },
-            KeyCode::Char('b') => {
-                if self.in_detail_page && !self.detail_items.is_empty() {
-                    if self.current_detail_index < self.detail_items.len() {
-                        let text = self.detail_items[self.current_detail_index].text.clone();
-                        thread::spawn(move || {
-                            thread::sleep(Duration::from_secs(2));
-                            // 需要一个方式来获取应用的模拟键盘输出方法
-                            // 这里直接使用utils模块中的函数
-                            utils::simulate_keyboard_output(&text);
-                        });
-                    }
-                } else if self.in_detail_view && !self.detail_items.is_empty() {
-                    if let Some(selected) = self.detail_state.selected() {
-                        if selected < self.detail_items.len() {
-                            let text = self.detail_items[selected].text.clone();
-                            thread::spawn(move || {
-                                thread::sleep(Duration::from_secs(2));
-                                utils::simulate_keyboard_output(&text);
-                            });
-                        }
+            }
+            (AppState::EditMode, InputEvent::PageDown) => {
+                for _ in 0..self.text_view.page {
+                    self.editor.move_down();
+                }
+            }
+            (AppState::EditMode, InputEvent::ToggleWrap) => self.text_view.toggle_wrap(),
+            (AppState::EditMode, InputEvent::ToggleLineNumbers) => {
+                self.text_view.line_numbers = !self.text_view.line_numbers
+            }
+            (AppState::EditMode, InputEvent::CharacterInput(c)) => self.editor.insert_char(c),
+            (AppState::EditMode, InputEvent::Undo) => {
+                self.editor.undo();
+            }
+            (AppState::EditMode, InputEvent::Redo) => {
+                self.editor.redo();
+            }
+            (AppState::EditMode, _) => {}
+
+            // 保存模式
+            (AppState::SaveMode, InputEvent::SaveSelection) => self.confirm_save(),
+            (AppState::SaveMode, InputEvent::DiscardEdit) => self.discard_edit(),
+            (AppState::SaveMode, InputEvent::Escape) => self.resume_editing(),
+            (AppState::SaveMode, _) => {}
+
+            // 填写模板变量：Enter 确认并输出，Esc 取消，上下切换变量
+            (AppState::PromptMode, InputEvent::Enter) => self.submit_prompt(),
+            (AppState::PromptMode, InputEvent::Escape) => self.cancel_prompt(),
+            (AppState::PromptMode, event) => {
+                if let Some(form) = &mut self.prompt {
+                    match event {
+                        InputEvent::Up => form.previous_field(),
+                        InputEvent::Down => form.next_field(),
+                        InputEvent::Left => form.editor.move_left(),
+                        InputEvent::Right => form.editor.move_right(),
+                        InputEvent::Home => form.editor.move_home(),
+                        InputEvent::End => form.editor.move_end(),
+                        InputEvent::WordLeft => form.editor.move_word_left(),
+                        InputEvent::WordRight => form.editor.move_word_right(),
+                        InputEvent::Backspace => form.editor.backspace(),
+                        InputEvent::Delete => form.editor.delete(),
+                        InputEvent::CharacterInput(c) => form.editor.insert_char(c),
+                        _ => {}
                     }
                 }
             }
-            KeyCode::Char('w') => {
-                if self.in_save_mode {
-                    if self.current_detail_index < self.detail_items.len() {
-                        let buffer = self.edit_buffer.clone();
-                        match self.current_detail_selection {
-                            DetailSelection::Title => {
-                                self.detail_items[self.current_detail_index].title = buffer;
-                            },
-                            DetailSelection::Describe => {
-                                self.detail_items[self.current_detail_index].describe = buffer;
-                            },
-                            DetailSelection::Text => {
-                                self.detail_items[self.current_detail_index].text = buffer;
-                            },
-                        }
+
+            // 搜索：输入关键字后立即更新结果，上下选择结果，Enter 打开
+            (AppState::SearchMode, InputEvent::Enter) => self.open_search_hit(),
+            (AppState::SearchMode, InputEvent::Escape) => self.cancel_search(),
+            (AppState::SearchMode, event) => {
+                if let Some(search) = &mut self.search {
+                    match event {
+                        InputEvent::Up => search.previous(),
+                        InputEvent::Down => search.next(),
+                        InputEvent::Left => search.editor.move_left(),
+                        InputEvent::Right => search.editor.move_right(),
+                        InputEvent::Home => search.editor.move_home(),
+                        InputEvent::End => search.editor.move_end(),
+                        InputEvent::Backspace => search.editor.backspace(),
+                        InputEvent::Delete => search.editor.delete(),
+                        InputEvent::CharacterInput(c) => search.editor.insert_char(c),
+                        _ => {}
                     }
-                    self.in_save_mode = false;
-                    self.edit_buffer.clear();
-                } else if self.in_edit_mode {
-                    self.edit_buffer.push('w');
-                } else if self.in_detail_page {
-                    if !self.detail_items.is_empty() {
-                        let old_index = self.current_detail_index;
-                        self.current_detail_index = (self.current_detail_index + 1) % self.detail_items.len();
-                        if old_index != self.current_detail_index {
-                            self.edit_buffer.clear();
-                        }
+                    if matches!(event, InputEvent::Backspace | InputEvent::Delete | InputEvent::CharacterInput(_)) {
+                        search.update(&self.items);
                     }
-                } else if self.in_detail_view {
-                    self.next_detail();
-                } else {
-                    self.next();
                 }
-            },
-            KeyCode::Char(c) => {
-                if self.in_edit_mode {
-                    // 编辑模式下，只允许特定功能键执行功能，其他字符正常输入
-                    match c {
-                        // 在编辑模式下，Esc键退出编辑模式
-                        'q' => {
-                            self.in_edit_mode = false;
-                            self.in_save_mode = true;
-                        },
-                        // 在编辑模式下，Ctrl+S保存并退出
-                        'w' => {
-                            if self.current_detail_index < self.detail_items.len() {
-                                let buffer = self.edit_buffer.clone();
-                                match self.current_detail_selection {
-                                    DetailSelection::Title => {
-                                        self.detail_items[self.current_detail_index].title = buffer;
-                                    },
-                                    DetailSelection::Describe => {
-                                        self.detail_items[self.current_detail_index].describe = buffer;
-                                    },
-                                    DetailSelection::Text => {
-                                        self.detail_items[self.current_detail_index].text = buffer;
-                                    },
-                                }
-                            }
-                            self.in_edit_mode = false;
-                            self.edit_buffer.clear();
-                        },
-                        // 其他字符正常输入到编辑缓冲区
-                        _ => {
-                            self.edit_buffer.push(c);
-                        }
-                    }
-                } else {
-                    // 非编辑模式下，所有功能键正常执行
-                    match c {
-                        'a' => {
-                            self.items.push(format!("新订阅项 {}", self.items.len() + 1));
-                        },
-                        'd' => {
-                            if !self.items.is_empty() {
-                                let selected = self.state.selected().unwrap_or(0);
-                                self.items.remove(selected);
-                                
-                                if self.items.is_empty() {
-                                    self.state.select(None);
-                                } else {
-                                    let new_selected = if selected >= self.items.len() {
-                                        self.items.len() - 1
-                                    } else {
-                                        selected
-                                    };
-                                    self.state.select(Some(new_selected));
-                                }
-                            }
-                        },
-                        'r' => {
-                            self.items.sort();
-                        },
-                        't' => {
-                            self.toggle_pin();
-                        },
-                        'v' => {
-                            if self.in_detail_page && !self.in_edit_mode && !self.in_save_mode {
-                                if self.current_detail_index < self.detail_items.len() {
-                                    let current_item = &self.detail_items[self.current_detail_index];
-                                    self.edit_buffer = match self.current_detail_selection {
-                                        DetailSelection::Title => current_item.title.clone(),
-                                        DetailSelection::Describe => current_item.describe.clone(),
-                                        DetailSelection::Text => current_item.text.clone(),
-                                    };
-                                    self.in_edit_mode = true;
-                                }
-                            }
-                        },
-                        'b' => {
-                            if self.in_detail_page && !self.detail_items.is_empty() {
-                                if self.current_detail_index < self.detail_items.len() {
-                                    let text = self.detail_items[self.current_detail_index].text.clone();
-                                    thread::spawn(move || {
-                                        thread::sleep(Duration::from_secs(2));
-                                        utils::simulate_keyboard_output(&text);
-                                    });
-                                }
-                            } else if self.in_detail_view && !self.detail_items.is_empty() {
-                                if let Some(selected) = self.detail_state.selected() {
-                                    if selected < self.detail_items.len() {
-                                        let text = self.detail_items[selected].text.clone();
-                                        thread::spawn(move || {
-                                            thread::sleep(Duration::from_secs(2));
-                                            utils::simulate_keyboard_output(&text);
-                                        });
-                                    }
-                                }
-                            }
-                        },
-                        'w' => {
-                            if self.in_save_mode {
-                                if self.current_detail_index < self.detail_items.len() {
-                                    let buffer = self.edit_buffer.clone();
-                                    match self.current_detail_selection {
-                                        DetailSelection::Title => {
-                                            self.detail_items[self.current_detail_index].title = buffer;
-                                        },
-                                        DetailSelection::Describe => {
-                                            self.detail_items[self.current_detail_index].describe = buffer;
-                                        },
-                                        DetailSelection::Text => {
-                                            self.detail_items[self.current_detail_index].text = buffer;
-                                        },
-                                    }
-                                }
-                                self.in_save_mode = false;
-                                self.edit_buffer.clear();
-                            } else if self.in_detail_page {
-                                if !self.detail_items.is_empty() {
-                                    let old_index = self.current_detail_index;
-                                    self.current_detail_index = (self.current_detail_index + 1) % self.detail_items.len();
-                                    if old_index != self.current_detail_index {
-                                        self.edit_buffer.clear();
-                                    }
-                                }
-                            } else if self.in_detail_view {
-                                self.next_detail();
-                            } else {
-                                self.next();
-                            }
-                        },
+            }
+
+            // 命令面板：输入关键字过滤动作，Enter 执行
+            (AppState::PaletteMode, InputEvent::Enter) => return self.run_palette_action(),
+            (AppState::PaletteMode, InputEvent::Escape) => self.close_palette(),
+            (AppState::PaletteMode, event) => {
+                if let Some(palette) = &mut self.palette {
+                    match event {
+                        InputEvent::Up => palette.previous(),
+                        InputEvent::Down => palette.next(),
+                        InputEvent::Left => palette.editor.move_left(),
+                        InputEvent::Right => palette.editor.move_right(),
+                        InputEvent::Home => palette.editor.move_home(),
+                        InputEvent::End => palette.editor.move_end(),
+                        InputEvent::Backspace => palette.editor.backspace(),
+                        InputEvent::Delete => palette.editor.delete(),
+                        InputEvent::CharacterInput(c) => palette.editor.insert_char(c),
                         _ => {}
                     }
+                    if matches!(event, InputEvent::Backspace | InputEvent::Delete | InputEvent::CharacterInput(_)) {
+                        palette.update();
+                    }
                 }
-            },
-            KeyCode::Backspace => {
-                if self.in_edit_mode {
-                    self.edit_buffer.pop();
-                }
-            },
+            }
+
+            // 以下为浏览视图：主视图、详情视图、详情页面
+            (_, InputEvent::Quit | InputEvent::Escape) => return self.go_back(),
+            (_, InputEvent::TogglePinned) => self.toggle_pin(),
+            (_, InputEvent::Search) => self.start_search(),
+            (_, InputEvent::Undo) => self.undo(),
+            (_, InputEvent::Redo) => self.redo(),
+
+            // 主视图
+            (AppState::MainView, InputEvent::Down) => self.next(),
+            (AppState::MainView, InputEvent::Up) => self.previous(),
+            (AppState::MainView, InputEvent::Enter) => self.enter_detail_view(),
+            (AppState::MainView, InputEvent::AddItem) => self.modify(App::add_subscription),
+            (AppState::MainView, InputEvent::DeleteItem) => self.modify(App::delete_selected_subscription),
+            (AppState::MainView, InputEvent::Refresh) => self.modify(App::sort_subscriptions),
+
+            // 详情视图
+            (AppState::DetailView, InputEvent::Down) => self.next_detail(),
+            (AppState::DetailView, InputEvent::Up) => self.previous_detail(),
+            (AppState::DetailView, InputEvent::Enter) => self.enter_detail_page(),
+            (AppState::DetailView, InputEvent::AddItem) => self.modify(App::add_detail),
+            (AppState::DetailView, InputEvent::DeleteItem) => self.modify(App::delete_selected_detail),
+            (AppState::DetailView, InputEvent::SimulateOutput) => self.start_output(PromptAction::Type),
+            (AppState::DetailView, InputEvent::CopyText) => self.start_output(PromptAction::Copy),
+
+            // 详情页面
+            (AppState::DetailPage, InputEvent::Down) => self.next_selection(),
+            (AppState::DetailPage, InputEvent::Up) => self.previous_selection(),
+            (AppState::DetailPage, InputEvent::EditMode) => self.start_editing(),
+            (AppState::DetailPage, InputEvent::DeleteItem) => self.modify(App::clear_selected_field),
+            (AppState::DetailPage, InputEvent::SimulateOutput) => self.start_output(PromptAction::Type),
+            (AppState::DetailPage, InputEvent::CopyText) => self.start_output(PromptAction::Copy),
+            (AppState::DetailPage, InputEvent::NextDetail) => self.next_detail_page(),
+            (AppState::DetailPage, InputEvent::TogglePreview) => self.toggle_preview(),
+            (AppState::DetailPage, InputEvent::ScrollUp) => self.text_view.scroll_up(1),
+            (AppState::DetailPage, InputEvent::ScrollDown) => self.text_view.scroll_down(1),
+            (AppState::DetailPage, InputEvent::ScrollLeft) => self.text_view.scroll_left(),
+            (AppState::DetailPage, InputEvent::ScrollRight) => self.text_view.scroll_right(),
+            (AppState::DetailPage, InputEvent::PageUp) => self.text_view.scroll_up(self.text_view.page),
+            (AppState::DetailPage, InputEvent::PageDown) => self.text_view.scroll_down(self.text_view.page),
+            (AppState::DetailPage, InputEvent::ToggleWrap) => self.text_view.toggle_wrap(),
+            (AppState::DetailPage, InputEvent::ToggleLineNumbers) => {
+                self.text_view.line_numbers = !self.text_view.line_numbers
+            }
+
+            // 浏览视图中都可以取消输出任务
+            (_, InputEvent::CancelOutput) => self.cancel_typing(),
+
             _ => {}
         }
+        true
+    }
+}
+
+/// 删除列表项后，计算新的选中位置
+fn clamp_selection(selected: usize, len: usize) -> Option<usize> {
+    if len == 0 {
+        None
+    } else {
+        Some(selected.min(len - 1))
+    }
+}
+
+/// 决定详情页面文本区块内容的状态，见 [`App::text_source`]
+type TextSource = (AppState, (usize, usize), DetailSelection, u64, u64, bool);
+
+/// 盖在其他视图上的浮层，关闭后回到打开时的视图
+fn is_overlay(state: AppState) -> bool {
+    matches!(
+        state,
+        AppState::PromptMode | AppState::SearchMode | AppState::PaletteMode | AppState::HelpMode
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clipboard::MemoryClipboard;
+    use crate::output::{Key, KeyAction, Modifiers};
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    /// 按下一个按键，经过按键映射后交给应用处理
+    fn press(app: &mut App, code: KeyCode) {
+        let event = translate_key(&app.keymap, app.app_state, KeyEvent::new(code, KeyModifiers::NONE));
+        app.apply(event);
+    }
+
+    fn recording_app(text: &str) -> App {
+        let mut app = App::new();
+        app.output_sink = SinkKind::Recorder;
+        app.typing_settings.start_delay_ms = 0;
+        app.items[0].details[0].text = text.to_string();
+        app
     }
 
-    pub fn run(&mut self, terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>) -> io::Result<()> {
+    /// 从事件队列中等待输出任务结束
+    fn wait_typing(app: &mut App) -> TypingOutcome {
         loop {
-            terminal.draw(|f| ui::draw(f, self))?;
-
-            if let Event::Key(key) = event::read()? {
-                if key.kind == KeyEventKind::Press {
-                    match key.code {
-                        KeyCode::Char('q') => {
-                            if self.in_save_mode {
-                                self.in_save_mode = false;
-                            } else if self.in_edit_mode {
-                                self.edit_buffer.push('q');
-                            } else if self.in_detail_page {
-                                self.in_detail_page = false;
-                            } else if self.in_detail_view {
-                                self.in_detail_view = false;
-                            } else {
-                                return Ok(());
-                            }
-                        }
-                        _ => {
-                            self.handle_key_event(key.code);
-                        }
-                    }
-                }
+            if let AppEvent::Typing(TypingUpdate::Finished(outcome)) = app.events.next().unwrap() {
+                app.typing = None;
+                return outcome;
             }
         }
     }
-}
\ No newline at end of file
+
+    fn detail_titles(app: &App) -> Vec<&str> {
+        app.details().iter().map(|d| d.title.as_str()).collect()
+    }
+
+    /// 第一个订阅带三个详情项的应用，已进入该订阅的详情视图
+    fn three_details_app() -> App {
+        let mut app = App::new();
+        app.items[0].details = ["A", "B", "C"].map(|title| DetailItem::new(title, "", "")).to_vec();
+        press(&mut app, KeyCode::Enter);
+        app
+    }
+
+    #[test]
+    fn undo_and_redo_list_operations_in_order() {
+        let mut app = three_details_app();
+        press(&mut app, KeyCode::Char('a'));
+        press(&mut app, KeyCode::Char('d'));
+        press(&mut app, KeyCode::Char('d'));
+        assert_eq!(detail_titles(&app), ["A", "B"]);
+        assert_eq!(app.history.undo_depth(), 3);
+
+        app.apply(InputEvent::Undo);
+        assert_eq!(detail_titles(&app), ["A", "B", "C"]);
+        app.apply(InputEvent::Undo);
+        assert_eq!(detail_titles(&app), ["A", "B", "C", "新详情项 4"]);
+        app.apply(InputEvent::Redo);
+        assert_eq!(detail_titles(&app), ["A", "B", "C"]);
+        assert_eq!((app.history.undo_depth(), app.history.redo_depth()), (2, 1));
+
+        // 新的修改清空重做记录
+        press(&mut app, KeyCode::Char('a'));
+        assert_eq!(app.history.redo_depth(), 0);
+        app.apply(InputEvent::Redo);
+        assert_eq!(app.status_message.as_deref(), Some("没有可重做的操作"));
+        assert_eq!(detail_titles(&app).len(), 4);
+    }
+
+    #[test]
+    fn undo_leaves_detail_page_when_viewed_item_moved() {
+        let mut app = three_details_app();
+        press(&mut app, KeyCode::Char('d'));
+        // 删除第一个后打开 B，撤销删除后 B 回到第二个位置
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.current_detail().unwrap().title, "B");
+
+        app.apply(InputEvent::Undo);
+        assert_eq!(app.app_state, AppState::DetailView);
+        assert_eq!(detail_titles(&app), ["A", "B", "C"]);
+        assert!(app.status_message.is_some());
+    }
+
+    #[test]
+    fn undo_leaves_detail_page_when_viewed_item_is_removed() {
+        let mut app = three_details_app();
+        press(&mut app, KeyCode::Char('a'));
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.current_detail().unwrap().title, "新详情项 4");
+
+        app.apply(InputEvent::Undo);
+        assert_eq!(app.app_state, AppState::DetailView);
+        assert_eq!(detail_titles(&app), ["A", "B", "C"]);
+        assert!(app.status_message.is_some());
+    }
+
+    #[test]
+    fn undo_of_edit_stays_on_detail_page() {
+        let mut app = three_details_app();
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Char('d'));
+        assert_eq!(app.current_detail().unwrap().title, "");
+
+        app.apply(InputEvent::Undo);
+        assert_eq!(app.app_state, AppState::DetailPage);
+        assert_eq!(app.current_detail().unwrap().title, "B");
+        assert!(app.status_message.is_none());
+    }
+
+    #[test]
+    fn simulate_output_from_detail_view_types_selected_item() {
+        let mut app = recording_app("ab\n\tC");
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Char('b'));
+        assert!(matches!(wait_typing(&mut app), TypingOutcome::Completed));
+
+        assert_eq!(
+            app.recorder.take(),
+            vec![
+                KeyAction::Char('a'),
+                KeyAction::Char('b'),
+                KeyAction::press(Key::Enter),
+                KeyAction::press(Key::Tab),
+                KeyAction::Char('C'),
+            ]
+        );
+    }
+
+    #[test]
+    fn simulate_output_from_detail_page_types_text_field() {
+        let mut app = recording_app("x y");
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Char('b'));
+        assert!(matches!(wait_typing(&mut app), TypingOutcome::Completed));
+
+        assert_eq!(
+            app.recorder.take(),
+            vec![KeyAction::Char('x'), KeyAction::Char(' '), KeyAction::Char('y')]
+        );
+    }
+
+    #[test]
+    fn simulate_output_in_main_view_does_nothing() {
+        let mut app = recording_app("abc");
+        press(&mut app, KeyCode::Char('b'));
+
+        assert!(app.typing.is_none());
+        assert!(app.recorder.take().is_empty());
+    }
+
+    #[test]
+    fn cancel_key_stops_job_during_countdown() {
+        let mut app = recording_app("abc");
+        app.typing_settings.start_delay_ms = 60_000;
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Char('b'));
+        press(&mut app, KeyCode::Char('x'));
+
+        assert!(matches!(wait_typing(&mut app), TypingOutcome::Cancelled));
+        assert!(app.recorder.take().is_empty());
+    }
+
+    #[test]
+    fn only_one_job_runs_at_a_time() {
+        let mut app = recording_app("abc");
+        app.typing_settings.start_delay_ms = 60_000;
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Char('b'));
+        press(&mut app, KeyCode::Char('b'));
+
+        assert!(app.status_message.is_some());
+        press(&mut app, KeyCode::Char('x'));
+        assert!(matches!(wait_typing(&mut app), TypingOutcome::Cancelled));
+    }
+
+    #[test]
+    fn finished_job_reports_status() {
+        let mut app = recording_app("abc");
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Char('b'));
+        while app.typing.is_some() {
+            let event = app.events.next().unwrap();
+            app.handle_event(event);
+        }
+
+        assert_eq!(app.status_message.as_deref(), Some("输出完成"));
+        assert_eq!(app.recorder.take().len(), 3);
+    }
+
+    #[test]
+    fn typing_settings_are_edited_from_detail_page() {
+        let mut app = recording_app("abc");
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Up);
+        assert_eq!(app.current_detail_selection, DetailSelection::Typing);
+
+        press(&mut app, KeyCode::Char('v'));
+        press(&mut app, KeyCode::Enter);
+        for c in "key_delay_ms = 50".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        press(&mut app, KeyCode::Esc);
+        press(&mut app, KeyCode::Char('w'));
+
+        assert_eq!(app.app_state, AppState::DetailPage);
+        assert_eq!(app.items[0].details[0].typing.key_delay_ms, Some(50));
+        assert_eq!(app.items[0].details[0].typing.start_delay_ms, None);
+    }
+
+    #[test]
+    fn invalid_typing_settings_return_to_edit_mode() {
+        let mut app = recording_app("abc");
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Up);
+        press(&mut app, KeyCode::Char('v'));
+        for c in "\nspeed = 3".chars() {
+            press(&mut app, if c == '\n' { KeyCode::Enter } else { KeyCode::Char(c) });
+        }
+        press(&mut app, KeyCode::Esc);
+        press(&mut app, KeyCode::Char('w'));
+
+        assert_eq!(app.app_state, AppState::EditMode);
+        assert!(app.status_message.is_some());
+        assert!(app.items[0].details[0].typing.is_empty());
+    }
+
+    #[test]
+    fn simulate_output_sends_markup_actions() {
+        let mut app = recording_app("{CTRL+A}{{x{WAIT 5}{ENTER}");
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Char('b'));
+        assert!(matches!(wait_typing(&mut app), TypingOutcome::Completed));
+
+        assert_eq!(
+            app.recorder.take(),
+            vec![
+                KeyAction::Key(Key::Char('a'), Modifiers { ctrl: true, ..Modifiers::default() }),
+                KeyAction::Char('{'),
+                KeyAction::Char('x'),
+                KeyAction::press(Key::Enter),
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_markup_is_reported() {
+        let mut app = recording_app("abc{TAB");
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Char('b'));
+
+        assert!(app.typing.is_none());
+        assert!(app.status_message.unwrap().contains("第 1 行第 4 列"));
+    }
+
+    fn typed_text(app: &App) -> String {
+        app.recorder
+            .take()
+            .into_iter()
+            .map(|action| match action {
+                KeyAction::Char(c) => c,
+                _ => '?',
+            })
+            .collect()
+    }
+
+    #[test]
+    fn template_variables_are_prompted_before_output() {
+        let mut app = recording_app("ssh ${user:root}@${host}");
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Char('b'));
+        assert_eq!(app.app_state, AppState::PromptMode);
+        assert!(app.typing.is_none());
+
+        press(&mut app, KeyCode::Tab);
+        for c in "db{1}".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.app_state, AppState::DetailView);
+        assert!(matches!(wait_typing(&mut app), TypingOutcome::Completed));
+        assert_eq!(typed_text(&app), "ssh root@db{1}");
+
+        // 再次输出时填入上次使用的值
+        press(&mut app, KeyCode::Char('b'));
+        let form = app.prompt.as_ref().unwrap();
+        assert_eq!(form.value(0), "root");
+        assert_eq!(form.value(1), "db{1}");
+    }
+
+    #[test]
+    fn escape_closes_prompt_without_output() {
+        let mut app = recording_app("${name}");
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Char('b'));
+        press(&mut app, KeyCode::Char('x'));
+        press(&mut app, KeyCode::Esc);
+
+        assert_eq!(app.app_state, AppState::DetailPage);
+        assert!(app.prompt.is_none());
+        assert!(app.typing.is_none());
+        assert!(app.items[0].details[0].variables.is_empty());
+    }
+
+    #[test]
+    fn builtins_use_injected_context_and_counter_advances() {
+        let mut app = recording_app("${date}#${counter} ${env:USER}");
+        app.template_context = Box::new(template::FixedContext::default());
+        press(&mut app, KeyCode::Enter);
+
+        press(&mut app, KeyCode::Char('b'));
+        assert!(matches!(wait_typing(&mut app), TypingOutcome::Completed));
+        assert_eq!(typed_text(&app), "2024-03-05#1 alice");
+
+        press(&mut app, KeyCode::Char('b'));
+        assert!(matches!(wait_typing(&mut app), TypingOutcome::Completed));
+        assert_eq!(typed_text(&app), "2024-03-05#2 alice");
+        assert_eq!(app.items[0].details[0].counter, 2);
+    }
+
+    #[test]
+    fn builtin_errors_are_reported_without_output() {
+        let mut app = recording_app("${env:MISSING}");
+        app.template_context = Box::new(template::FixedContext::default());
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Char('b'));
+
+        assert!(app.typing.is_none());
+        assert!(app.status_message.unwrap().contains("MISSING"));
+    }
+
+    #[test]
+    fn includes_are_typed_and_cycles_reported() {
+        let mut app = recording_app("${include:Item 2: Build TUI applications/构建TUI应用}!");
+        app.items[1].details[0].text = "hi ${name:you}".to_string();
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Char('b'));
+        assert_eq!(app.app_state, AppState::PromptMode);
+        press(&mut app, KeyCode::Enter);
+        assert!(matches!(wait_typing(&mut app), TypingOutcome::Completed));
+        assert_eq!(typed_text(&app), "hi you!");
+
+        app.items[1].details[0].text = "${include:Item 1: Learn Rust/学习Rust}".to_string();
+        press(&mut app, KeyCode::Char('b'));
+        assert!(app.typing.is_none());
+        assert!(app.status_message.as_ref().unwrap().starts_with("循环引用"));
+    }
+
+    #[test]
+    fn preview_shows_expanded_text_without_consuming_counter() {
+        let mut app = recording_app("${greeting:hello} #${counter}");
+        app.template_context = Box::new(template::FixedContext::default());
+        app.items[0].details[0].variables.insert("greeting".to_string(), "hey".to_string());
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Enter);
+
+        press(&mut app, KeyCode::Char('p'));
+        assert_eq!(app.current_preview(), Some("hey #1"));
+        assert_eq!(app.items[0].details[0].counter, 0);
+
+        press(&mut app, KeyCode::Char('p'));
+        assert_eq!(app.current_preview(), None);
+    }
+
+    #[test]
+    fn copy_puts_expanded_plain_text_on_clipboard() {
+        let mut app = recording_app("ls{ENTER}{CTRL+C}${dir:/tmp}{{x}");
+        let clipboard = MemoryClipboard::default();
+        app.clipboard = Box::new(clipboard.clone());
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Char('c'));
+
+        // 有变量时也要先填写表单
+        assert_eq!(app.app_state, AppState::PromptMode);
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.app_state, AppState::DetailView);
+        assert_eq!(clipboard.text.lock().unwrap().as_deref(), Some("ls\n/tmp{x}"));
+        assert!(app.current_toast().unwrap().contains("已复制"));
+        assert!(app.typing.is_none());
+    }
+
+    #[test]
+    fn tick_expires_toast() {
+        let mut app = App::new();
+        app.show_toast("已复制".to_string());
+        assert!(app.handle_event(AppEvent::Tick));
+        assert_eq!(app.current_toast(), Some("已复制"));
+
+        app.toast.as_mut().unwrap().shown_at -= TOAST_DURATION;
+        app.handle_event(AppEvent::Tick);
+        assert_eq!(app.current_toast(), None);
+    }
+
+    #[test]
+    fn ignores_key_release_and_resize_events() {
+        let mut app = App::new();
+        let mut release = KeyEvent::new(KeyCode::Down, KeyModifiers::NONE);
+        release.kind = KeyEventKind::Release;
+
+        assert!(app.handle_event(AppEvent::Key(release)));
+        assert!(app.handle_event(AppEvent::Resize));
+        assert_eq!(app.state.selected(), Some(0));
+
+        assert!(app.handle_event(AppEvent::Key(KeyEvent::new(KeyCode::Down, KeyModifiers::NONE))));
+        assert_eq!(app.state.selected(), Some(1));
+    }
+
+    #[test]
+    fn search_jumps_to_matched_field_in_detail_page() {
+        let mut app = App::new();
+        press(&mut app, KeyCode::Char('/'));
+        assert_eq!(app.app_state, AppState::SearchMode);
+        for c in "drawui".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        let hit = app.search.as_ref().unwrap().selected_hit().unwrap();
+        assert_eq!((hit.subscription, hit.detail), (2, Some(0)));
+
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.app_state, AppState::DetailPage);
+        assert_eq!((app.current_subscription, app.current_detail_index), (2, 0));
+        assert_eq!(app.current_detail_selection, DetailSelection::Text);
+        assert!(app.search.is_none());
+    }
+
+    #[test]
+    fn escape_leaves_search_where_it_started() {
+        let mut app = App::new();
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Char('/'));
+        press(&mut app, KeyCode::Char('q'));
+        press(&mut app, KeyCode::Esc);
+
+        assert_eq!(app.app_state, AppState::DetailView);
+        assert!(app.search.is_none());
+    }
+
+    #[test]
+    fn palette_runs_filtered_action_in_previous_view() {
+        let mut app = App::new();
+        press(&mut app, KeyCode::Enter);
+        app.apply(InputEvent::Help);
+        assert_eq!(app.app_state, AppState::PaletteMode);
+        for c in "add".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        assert_eq!(app.palette.as_ref().unwrap().selected_event(), Some(InputEvent::AddItem));
+
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.app_state, AppState::DetailView);
+        assert!(app.palette.is_none());
+        assert_eq!(app.items[0].details.len(), 2);
+    }
+
+    #[test]
+    fn palette_quit_exits_from_main_view() {
+        let mut app = App::new();
+        app.apply(InputEvent::Help);
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(app.app_state, AppState::MainView);
+
+        app.apply(InputEvent::Help);
+        assert!(!app.apply(InputEvent::Enter));
+    }
+
+    #[test]
+    fn help_overlay_scrolls_and_returns_to_previous_view() {
+        let mut app = App::new();
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Char('?'));
+        assert_eq!(app.app_state, AppState::HelpMode);
+
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Up);
+        assert_eq!(app.help.as_ref().unwrap().scroll, 1);
+        press(&mut app, KeyCode::End);
+        press(&mut app, KeyCode::PageDown);
+
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(app.app_state, AppState::DetailView);
+        assert!(app.help.is_none());
+    }
+
+    #[test]
+    fn apply_moves_between_views() {
+        use AppState::*;
+        let mut app = App::new();
+        let steps = [
+            (InputEvent::Enter, DetailView),
+            (InputEvent::Enter, DetailPage),
+            (InputEvent::EditMode, EditMode),
+            (InputEvent::Escape, SaveMode),
+            (InputEvent::Escape, EditMode),
+            (InputEvent::Escape, SaveMode),
+            (InputEvent::DiscardEdit, DetailPage),
+            (InputEvent::EditMode, EditMode),
+            (InputEvent::Escape, SaveMode),
+            (InputEvent::SaveSelection, DetailPage),
+            (InputEvent::Search, SearchMode),
+            (InputEvent::Escape, DetailPage),
+            (InputEvent::Help, PaletteMode),
+            (InputEvent::Escape, DetailPage),
+            (InputEvent::ShowHelp, HelpMode),
+            (InputEvent::Escape, DetailPage),
+            (InputEvent::Quit, DetailView),
+            (InputEvent::Quit, MainView),
+        ];
+        for (event, expected) in steps {
+            assert!(app.apply(event));
+            assert_eq!(app.app_state, expected, "{:?}", event);
+        }
+        // 主视图中返回表示退出程序
+        assert!(!app.apply(InputEvent::Quit));
+    }
+
+    #[test]
+    fn apply_ignores_events_of_other_views() {
+        use AppState::*;
+        let cases = [
+            (MainView, InputEvent::EditMode),
+            (MainView, InputEvent::SaveSelection),
+            (MainView, InputEvent::NextDetail),
+            (MainView, InputEvent::OtherKey),
+            (DetailView, InputEvent::Refresh),
+            (DetailView, InputEvent::TogglePreview),
+            (DetailPage, InputEvent::AddItem),
+            (DetailPage, InputEvent::Enter),
+        ];
+        for (state, event) in cases {
+            let mut app = App::new();
+            while app.app_state != state {
+                app.apply(InputEvent::Enter);
+            }
+            let count = app.items.len();
+            let details = app.items[0].details.len();
+            assert!(app.apply(event));
+            assert_eq!(app.app_state, state, "{:?} 中的 {:?}", state, event);
+            assert_eq!((app.items.len(), app.items[0].details.len()), (count, details));
+        }
+    }
+
+    #[test]
+    fn palette_does_not_open_over_other_overlays() {
+        let mut app = App::new();
+        app.apply(InputEvent::Search);
+        app.apply(InputEvent::Help);
+        assert_eq!(app.app_state, AppState::SearchMode);
+        assert!(app.palette.is_none());
+
+        app.apply(InputEvent::Escape);
+        app.apply(InputEvent::Help);
+        app.apply(InputEvent::Help);
+        assert_eq!(app.app_state, AppState::PaletteMode);
+        // 关闭一次就回到主视图，不会回到另一个面板
+        app.apply(InputEvent::Escape);
+        assert_eq!(app.app_state, AppState::MainView);
+        assert!(app.palette.is_none());
+    }
+
+    #[test]
+    fn palette_and_help_do_not_nest() {
+        let mut app = App::new();
+        press(&mut app, KeyCode::Enter);
+        app.apply(InputEvent::Help);
+        app.apply(InputEvent::ShowHelp);
+        assert_eq!(app.app_state, AppState::PaletteMode);
+        assert!(app.help.is_none());
+        app.apply(InputEvent::Escape);
+
+        app.apply(InputEvent::ShowHelp);
+        app.apply(InputEvent::Help);
+        app.apply(InputEvent::ShowHelp);
+        assert_eq!(app.app_state, AppState::HelpMode);
+        assert!(app.palette.is_none());
+        app.apply(InputEvent::Escape);
+        assert_eq!(app.app_state, AppState::DetailView);
+
+        // 从命令面板打开帮助时先关闭面板，关闭帮助后回到原来的视图
+        app.apply(InputEvent::Help);
+        for c in "show_help".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.app_state, AppState::HelpMode);
+        assert!(app.palette.is_none());
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(app.app_state, AppState::DetailView);
+    }
+
+    #[test]
+    fn text_error_is_checked_again_after_data_changes() {
+        let mut app = three_details_app();
+        let reference = format!("${{include:{}/C}}", app.items[0].name);
+        app.items[0].details[0].text = reference.clone();
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.text_error(), None);
+        assert_eq!(app.text_error(), None);
+
+        // 删除被引用的详情项后，同样的文本也要重新检查
+        press(&mut app, KeyCode::Char('q'));
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Char('d'));
+        app.detail_state.select(Some(0));
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(detail_titles(&app)[app.current_detail_index], "A");
+        assert!(app.text_error().unwrap().contains("/C"));
+
+        // 编辑中只检查编辑器中的标记，每次输入后重新检查
+        for code in [KeyCode::Down, KeyCode::Down, KeyCode::Down, KeyCode::Char('v')] {
+            press(&mut app, code);
+        }
+        assert_eq!(app.app_state, AppState::EditMode);
+        assert_eq!(app.text_error(), None);
+        press(&mut app, KeyCode::Char('{'));
+        assert!(app.text_error().unwrap().starts_with("标记错误"));
+        press(&mut app, KeyCode::Backspace);
+        assert_eq!(app.text_error(), None);
+    }
+
+    /// 通过事件队列的入口处理一个按键，和主循环一样会请求高亮
+    fn key_event(app: &mut App, code: KeyCode) {
+        app.handle_event(AppEvent::Key(KeyEvent::new(code, KeyModifiers::NONE)));
+    }
+
+    /// 等待已经提交的高亮任务完成
+    fn finish_highlighting(app: &mut App) {
+        while app.highlighter.is_busy() {
+            if let AppEvent::Highlighted(result) = app.events.next().unwrap() {
+                app.highlighter.store(result);
+            }
+        }
+    }
+
+    #[test]
+    fn highlight_is_requested_only_when_text_changes() {
+        let mut app = App::new();
+        key_event(&mut app, KeyCode::Enter);
+        key_event(&mut app, KeyCode::Enter);
+        assert!(app.highlighter.is_busy());
+        finish_highlighting(&mut app);
+
+        // 空闲时的定时事件和不改变文本的按键不会重新提交
+        app.handle_event(AppEvent::Tick);
+        for code in [KeyCode::Down, KeyCode::Down, KeyCode::Down, KeyCode::Char('v'), KeyCode::Left] {
+            key_event(&mut app, code);
+        }
+        assert_eq!(app.app_state, AppState::EditMode);
+        assert_eq!(app.current_detail_selection, DetailSelection::Text);
+        let revision = app.editor.revision();
+        app.handle_event(AppEvent::Tick);
+        finish_highlighting(&mut app);
+
+        key_event(&mut app, KeyCode::Char('x'));
+        assert_eq!(app.editor.revision(), revision + 1);
+        assert!(app.highlighter.is_busy());
+        finish_highlighting(&mut app);
+        key_event(&mut app, KeyCode::Left);
+        app.handle_event(AppEvent::Tick);
+        assert!(!app.highlighter.is_busy());
+    }
+}
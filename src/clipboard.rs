@@ -0,0 +1,198 @@
+use std::env;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+#[cfg(test)]
+use std::sync::{Arc, Mutex};
+
+/// 本地剪贴板工具：程序名和参数
+type Tool = (&'static str, &'static [&'static str]);
+
+#[cfg(windows)]
+const COPY_TOOLS: &[Tool] = &[("clip", &[])];
+
+#[cfg(target_os = "macos")]
+const COPY_TOOLS: &[Tool] = &[("pbcopy", &[])];
+
+#[cfg(not(any(windows, target_os = "macos")))]
+const COPY_TOOLS: &[Tool] = &[
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+];
+
+#[cfg(windows)]
+const PASTE_TOOLS: &[Tool] = &[("powershell", &["-NoProfile", "-Command", "Get-Clipboard -Raw"])];
+
+#[cfg(target_os = "macos")]
+const PASTE_TOOLS: &[Tool] = &[("pbpaste", &[])];
+
+#[cfg(not(any(windows, target_os = "macos")))]
+const PASTE_TOOLS: &[Tool] = &[
+    ("wl-paste", &["--no-newline"]),
+    ("xclip", &["-selection", "clipboard", "-o"]),
+    ("xsel", &["--clipboard", "--output"]),
+];
+
+/// 复制文本到系统剪贴板的方式，测试时可以替换为内存中的剪贴板
+pub trait Clipboard {
+    /// 复制文本，返回实际使用的复制方式
+    fn copy(&mut self, text: &str) -> io::Result<&'static str>;
+}
+
+/// 通过 OSC 52 终端转义序列和本地剪贴板工具复制
+pub struct SystemClipboard;
+
+impl Clipboard for SystemClipboard {
+    /// 总是发送 OSC 52，使 SSH 和 tmux 中也能复制到本机；不在 SSH 中时再尝试本地工具
+    ///
+    /// 终端不支持 OSC 52 时会忽略该序列，无法得知是否成功，所以本地工具成功时优先报告本地工具。
+    fn copy(&mut self, text: &str) -> io::Result<&'static str> {
+        let osc52 = write_osc52(text, env::var_os("TMUX").is_some());
+        let over_ssh = env::var_os("SSH_TTY").is_some() || env::var_os("SSH_CONNECTION").is_some();
+        if !over_ssh && copy(text).is_ok() {
+            return Ok("本地剪贴板");
+        }
+        osc52.map(|_| "OSC 52")
+    }
+}
+
+/// 记录复制内容的剪贴板，克隆出的剪贴板共享同一份内容
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub struct MemoryClipboard {
+    pub text: Arc<Mutex<Option<String>>>,
+}
+
+#[cfg(test)]
+impl Clipboard for MemoryClipboard {
+    fn copy(&mut self, text: &str) -> io::Result<&'static str> {
+        *self.text.lock().unwrap() = Some(text.to_string());
+        Ok("内存")
+    }
+}
+
+/// 向终端发送 OSC 52 序列，由终端把文本放到剪贴板
+fn write_osc52(text: &str, in_tmux: bool) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    stdout.write_all(osc52_sequence(text, in_tmux).as_bytes())?;
+    stdout.flush()
+}
+
+/// OSC 52 序列，tmux 中需要包在 DCS 透传序列中并把其中的 ESC 写两次
+fn osc52_sequence(text: &str, in_tmux: bool) -> String {
+    let sequence = format!("\x1b]52;c;{}\x07", base64(text.as_bytes()));
+    if in_tmux {
+        format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+    } else {
+        sequence
+    }
+}
+
+/// 标准 Base64 编码（带填充）
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let bits = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(bits >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// 通过本地剪贴板工具复制文本，依次尝试可用的工具
+pub fn copy(text: &str) -> io::Result<()> {
+    let mut last_error = None;
+
+    for (program, args) in COPY_TOOLS {
+        match run_copy_tool(program, args, text) {
+            Ok(()) => return Ok(()),
+            Err(e) => last_error = Some(e),
+        }
+    }
+
+    Err(last_error.unwrap_or_else(|| {
+        io::Error::new(io::ErrorKind::Unsupported, "当前平台没有可用的剪贴板工具")
+    }))
+}
+
+/// 通过本地剪贴板工具读取剪贴板中的文本
+pub fn paste() -> io::Result<String> {
+    let mut last_error = None;
+
+    for (program, args) in PASTE_TOOLS {
+        match run_paste_tool(program, args) {
+            Ok(text) => return Ok(text),
+            Err(e) => last_error = Some(e),
+        }
+    }
+
+    Err(last_error.unwrap_or_else(|| {
+        io::Error::new(io::ErrorKind::Unsupported, "当前平台没有可用的剪贴板工具")
+    }))
+}
+
+fn run_paste_tool(program: &str, args: &[&str]) -> io::Result<String> {
+    let output = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()?;
+
+    if !output.status.success() {
+        return Err(io::Error::other(format!("{} 退出状态 {}", program, output.status)));
+    }
+    let mut text = String::from_utf8(output.stdout).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    // PowerShell 会在输出末尾加上换行
+    if cfg!(windows) && text.ends_with("\r\n") {
+        text.truncate(text.len() - 2);
+    }
+    Ok(text)
+}
+
+fn run_copy_tool(program: &str, args: &[&str], text: &str) -> io::Result<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+
+    let status = child.wait()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("{} 退出状态 {}", program, status)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_pads_partial_chunks() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64("中".as_bytes()), "5Lit");
+    }
+
+    #[test]
+    fn osc52_is_wrapped_for_tmux() {
+        assert_eq!(osc52_sequence("hi", false), "\x1b]52;c;aGk=\x07");
+        assert_eq!(osc52_sequence("hi", true), "\x1bPtmux;\x1b\x1b]52;c;aGk=\x07\x1b\\");
+    }
+}
@@ -0,0 +1,68 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::keymap::KeymapConfig;
+use crate::output::SinkKind;
+use crate::typing::TypingSettings;
+
+/// 配置文件名
+const CONFIG_FILE_NAME: &str = "config.toml";
+
+/// 用户配置，所有字段都可以省略
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub keymap: KeymapConfig,
+    pub output: OutputConfig,
+    pub typing: TypingSettings,
+}
+
+/// `[output]` 配置：模拟键盘输出的目标
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputConfig {
+    pub sink: SinkKind,
+}
+
+impl Config {
+    /// 用户配置目录下的默认配置文件
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("heillo").join(CONFIG_FILE_NAME))
+    }
+
+    /// 读取配置文件，文件不存在时使用默认配置
+    pub fn load(path: &Path) -> io::Result<Config> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(e) => return Err(e),
+        };
+
+        toml::from_str(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(content: &str) -> Result<Config, toml::de::Error> {
+        toml::from_str(content)
+    }
+
+    #[test]
+    fn parses_output_sink() {
+        assert_eq!(parse("[output]\nsink = \"clipboard\"").unwrap().output.sink, SinkKind::Clipboard);
+        assert_eq!(parse("[output]\nsink = \"stderr\"").unwrap().output.sink, SinkKind::Stderr);
+        assert_eq!(parse("[output]\nsink = \"stdout\"").unwrap().output.sink, SinkKind::Stderr);
+        assert_eq!(parse("").unwrap().output.sink, SinkKind::Auto);
+    }
+
+    #[test]
+    fn rejects_test_only_sink() {
+        assert!(parse("[output]\nsink = \"recorder\"").is_err());
+    }
+}
@@ -0,0 +1,448 @@
+use unicode_width::UnicodeWidthChar;
+
+use crate::history::History;
+
+/// 多行文本编辑器，光标位置以行号和行内字符序号表示
+pub struct Editor {
+    lines: Vec<String>,
+    row: usize,
+    col: usize,
+    /// 上下移动时希望保持的显示列，左右移动或输入后重置
+    preferred_x: Option<usize>,
+    history: History<Snapshot>,
+    /// 上一次修改的类型，连续的同类修改合并为一步撤销
+    last_edit: Option<EditKind>,
+    /// 内容每次修改（包括撤销和重做）时加一，用于判断缓存的结果是否过期
+    revision: u64,
+}
+
+/// 编辑器内容和光标位置的快照
+struct Snapshot {
+    lines: Vec<String>,
+    row: usize,
+    col: usize,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum EditKind {
+    Insert,
+    Backspace,
+    Delete,
+}
+
+impl Default for Editor {
+    fn default() -> Editor {
+        Editor::new("")
+    }
+}
+
+impl Editor {
+    /// 载入文本，光标放在末尾
+    pub fn new(text: &str) -> Editor {
+        let lines: Vec<String> = text.split('\n').map(|l| l.to_string()).collect();
+        let row = lines.len() - 1;
+        let col = lines[row].chars().count();
+        Editor {
+            lines,
+            row,
+            col,
+            preferred_x: None,
+            history: History::default(),
+            last_edit: None,
+            revision: 0,
+        }
+    }
+
+    pub fn text(&self) -> String {
+        self.lines.join("\n")
+    }
+
+    /// 光标所在的行和字符序号
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.row, self.col)
+    }
+
+    /// 光标在当前行中的显示列，宽字符占两列
+    pub fn cursor_x(&self) -> usize {
+        display_width(self.lines[self.row].chars().take(self.col))
+    }
+
+    pub fn insert_char(&mut self, c: char) {
+        if c == '\n' {
+            self.insert_newline();
+            return;
+        }
+
+        // 输入空白字符时开始新的撤销分组，使撤销按单词进行
+        self.begin_edit(EditKind::Insert, c.is_whitespace());
+        let at = self.byte_index(self.col);
+        self.lines[self.row].insert(at, c);
+        self.col += 1;
+        self.preferred_x = None;
+    }
+
+    pub fn insert_newline(&mut self) {
+        self.begin_edit(EditKind::Insert, true);
+        let at = self.byte_index(self.col);
+        let rest = self.lines[self.row].split_off(at);
+        self.lines.insert(self.row + 1, rest);
+        self.row += 1;
+        self.col = 0;
+        self.preferred_x = None;
+    }
+
+    /// 删除光标前的字符，行首时与上一行合并
+    pub fn backspace(&mut self) {
+        if self.col == 0 && self.row == 0 {
+            return;
+        }
+
+        self.begin_edit(EditKind::Backspace, self.col == 0);
+        if self.col > 0 {
+            self.col -= 1;
+            let at = self.byte_index(self.col);
+            self.lines[self.row].remove(at);
+        } else if self.row > 0 {
+            let line = self.lines.remove(self.row);
+            self.row -= 1;
+            self.col = self.line_len();
+            self.lines[self.row].push_str(&line);
+        }
+        self.preferred_x = None;
+    }
+
+    /// 删除光标后的字符，行尾时与下一行合并
+    pub fn delete(&mut self) {
+        if self.col == self.line_len() && self.row + 1 == self.lines.len() {
+            return;
+        }
+
+        self.begin_edit(EditKind::Delete, self.col == self.line_len());
+        if self.col < self.line_len() {
+            let at = self.byte_index(self.col);
+            self.lines[self.row].remove(at);
+        } else if self.row + 1 < self.lines.len() {
+            let line = self.lines.remove(self.row + 1);
+            self.lines[self.row].push_str(&line);
+        }
+        self.preferred_x = None;
+    }
+
+    pub fn move_left(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+        } else if self.row > 0 {
+            self.row -= 1;
+            self.col = self.line_len();
+        }
+        self.preferred_x = None;
+        self.last_edit = None;
+        self.revision += 1;
+    }
+
+    pub fn move_right(&mut self) {
+        if self.col < self.line_len() {
+            self.col += 1;
+        } else if self.row + 1 < self.lines.len() {
+            self.row += 1;
+            self.col = 0;
+        }
+        self.preferred_x = None;
+        self.last_edit = None;
+    }
+
+    pub fn move_up(&mut self) {
+        if self.row > 0 {
+            let x = self.preferred_x.unwrap_or_else(|| self.cursor_x());
+            self.row -= 1;
+            self.col = self.col_at_x(x);
+            self.preferred_x = Some(x);
+            self.last_edit = None;
+        }
+    }
+
+    pub fn move_down(&mut self) {
+        if self.row + 1 < self.lines.len() {
+            let x = self.preferred_x.unwrap_or_else(|| self.cursor_x());
+            self.row += 1;
+            self.col = self.col_at_x(x);
+            self.preferred_x = Some(x);
+            self.last_edit = None;
+        }
+    }
+
+    pub fn move_home(&mut self) {
+        self.col = 0;
+        self.preferred_x = None;
+        self.last_edit = None;
+    }
+
+    pub fn move_end(&mut self) {
+        self.col = self.line_len();
+        self.preferred_x = None;
+        self.last_edit = None;
+    }
+
+    /// 移动到上一个单词的开头，行首时跳到上一行末尾
+    pub fn move_word_left(&mut self) {
+        if self.col == 0 {
+            self.move_left();
+            return;
+        }
+
+        let chars: Vec<char> = self.lines[self.row].chars().collect();
+        let mut col = self.col;
+        while col > 0 && !is_word_char(chars[col - 1]) {
+            col -= 1;
+        }
+        while col > 0 && is_word_char(chars[col - 1]) {
+            col -= 1;
+        }
+        self.col = col;
+        self.preferred_x = None;
+        self.last_edit = None;
+    }
+
+    /// 移动到下一个单词的末尾，行尾时跳到下一行开头
+    pub fn move_word_right(&mut self) {
+        let chars: Vec<char> = self.lines[self.row].chars().collect();
+        if self.col == chars.len() {
+            self.move_right();
+            return;
+        }
+
+        let mut col = self.col;
+        while col < chars.len() && !is_word_char(chars[col]) {
+            col += 1;
+        }
+        while col < chars.len() && is_word_char(chars[col]) {
+            col += 1;
+        }
+        self.col = col;
+        self.preferred_x = None;
+        self.last_edit = None;
+    }
+
+    /// 撤销上一步修改，没有可撤销的修改时返回 false
+    pub fn undo(&mut self) -> bool {
+        match self.history.undo(self.snapshot()) {
+            Some(snapshot) => {
+                self.restore(snapshot);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 重做上一步撤销的修改
+    pub fn redo(&mut self) -> bool {
+        match self.history.redo(self.snapshot()) {
+            Some(snapshot) => {
+                self.restore(snapshot);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    pub fn undo_depth(&self) -> usize {
+        self.history.undo_depth()
+    }
+
+    pub fn redo_depth(&self) -> usize {
+        self.history.redo_depth()
+    }
+
+    /// 修改前记录快照；与上一次修改同类且不是分组边界时合并到同一步
+    fn begin_edit(&mut self, kind: EditKind, boundary: bool) {
+        if boundary || self.last_edit != Some(kind) {
+            let snapshot = self.snapshot();
+            self.history.record(snapshot);
+        }
+        self.last_edit = Some(kind);
+        self.revision += 1;
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            lines: self.lines.clone(),
+            row: self.row,
+            col: self.col,
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.lines = snapshot.lines;
+        self.row = snapshot.row;
+        self.col = snapshot.col;
+        self.preferred_x = None;
+        self.last_edit = None;
+    }
+
+    fn line_len(&self) -> usize {
+        self.lines[self.row].chars().count()
+    }
+
+    /// 当前行中第 col 个字符的字节位置
+    fn byte_index(&self, col: usize) -> usize {
+        let line = &self.lines[self.row];
+        line.char_indices()
+            .nth(col)
+            .map(|(i, _)| i)
+            .unwrap_or(line.len())
+    }
+
+    /// 当前行中显示列不超过 x 的最后一个字符位置
+    fn col_at_x(&self, x: usize) -> usize {
+        let mut width = 0;
+        for (i, c) in self.lines[self.row].chars().enumerate() {
+            let w = c.width().unwrap_or(0);
+            if width + w > x {
+                return i;
+            }
+            width += w;
+        }
+        self.line_len()
+    }
+}
+
+/// 字符序列的显示宽度
+pub fn display_width(chars: impl Iterator<Item = char>) -> usize {
+    chars.map(|c| c.width().unwrap_or(0)).sum()
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn type_text(editor: &mut Editor, text: &str) {
+        text.chars().for_each(|c| editor.insert_char(c));
+    }
+
+    /// 光标放在第 row 行开头
+    fn at_line_start(text: &str, row: usize) -> Editor {
+        let mut editor = Editor::new(text);
+        while editor.cursor().0 > row {
+            editor.move_up();
+        }
+        editor.move_home();
+        editor
+    }
+
+    #[test]
+    fn wide_chars_take_two_columns() {
+        assert_eq!(display_width("a中b😀".chars()), 6);
+
+        let mut editor = Editor::new("a中b😀");
+        assert_eq!((editor.cursor(), editor.cursor_x()), ((0, 4), 6));
+        editor.move_left();
+        assert_eq!((editor.cursor(), editor.cursor_x()), ((0, 3), 4));
+        editor.move_left();
+        editor.move_left();
+        assert_eq!((editor.cursor(), editor.cursor_x()), ((0, 1), 1));
+    }
+
+    #[test]
+    fn vertical_moves_keep_display_column() {
+        let mut editor = Editor::new("中文字\nabcdef");
+        editor.move_up();
+        assert_eq!((editor.cursor(), editor.cursor_x()), ((0, 3), 6));
+
+        let mut editor = Editor::new("中文字\nabc");
+        // 第 3 列落在宽字符中间时停在该字符前面
+        editor.move_up();
+        assert_eq!((editor.cursor(), editor.cursor_x()), ((0, 1), 2));
+        editor.move_down();
+        assert_eq!(editor.cursor(), (1, 3));
+    }
+
+    #[test]
+    fn word_motion_skips_punctuation_and_crosses_lines() {
+        let mut editor = at_line_start("foo.bar(baz)\nqux", 0);
+        let mut stops = Vec::new();
+        for _ in 0..5 {
+            editor.move_word_right();
+            stops.push(editor.cursor());
+        }
+        assert_eq!(stops, [(0, 3), (0, 7), (0, 11), (0, 12), (1, 0)]);
+
+        let mut stops = Vec::new();
+        for _ in 0..4 {
+            editor.move_word_left();
+            stops.push(editor.cursor());
+        }
+        assert_eq!(stops, [(0, 12), (0, 8), (0, 4), (0, 0)]);
+    }
+
+    #[test]
+    fn backspace_at_line_start_joins_lines() {
+        let mut editor = at_line_start("ab\ncd", 1);
+        editor.backspace();
+        assert_eq!((editor.text().as_str(), editor.cursor()), ("abcd", (0, 2)));
+
+        editor.move_home();
+        editor.backspace();
+        assert_eq!((editor.text().as_str(), editor.cursor()), ("abcd", (0, 0)));
+    }
+
+    #[test]
+    fn delete_at_line_end_joins_lines() {
+        let mut editor = at_line_start("ab\ncd", 0);
+        editor.move_end();
+        editor.delete();
+        assert_eq!((editor.text().as_str(), editor.cursor()), ("abcd", (0, 2)));
+
+        editor.move_end();
+        editor.delete();
+        assert_eq!(editor.text(), "abcd");
+    }
+
+    #[test]
+    fn typing_run_undoes_as_one_step() {
+        let mut editor = Editor::new("");
+        type_text(&mut editor, "abc");
+        assert_eq!(editor.undo_depth(), 1);
+        assert!(editor.undo());
+        assert_eq!(editor.text(), "");
+        assert!(editor.redo());
+        assert_eq!((editor.text().as_str(), editor.cursor()), ("abc", (0, 3)));
+    }
+
+    #[test]
+    fn newline_and_whitespace_start_new_undo_steps() {
+        let mut editor = Editor::new("");
+        type_text(&mut editor, "ab");
+        editor.insert_newline();
+        type_text(&mut editor, "cd ef");
+
+        let mut texts = Vec::new();
+        while editor.undo() {
+            texts.push(editor.text());
+        }
+        assert_eq!(texts, ["ab\ncd", "ab", ""]);
+    }
+
+    #[test]
+    fn switching_edit_kind_starts_new_undo_step() {
+        let mut editor = Editor::new("");
+        type_text(&mut editor, "abc");
+        editor.backspace();
+        editor.backspace();
+        assert!(editor.undo());
+        assert_eq!(editor.text(), "abc");
+
+        // 移动光标后的输入不与之前的输入合并
+        editor.move_left();
+        type_text(&mut editor, "x");
+        assert!(editor.undo());
+        assert_eq!(editor.text(), "abc");
+    }
+}
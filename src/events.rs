@@ -0,0 +1,166 @@
+use crossterm::event::{Event, KeyEvent};
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::highlight::Highlighted;
+use crate::input::EventSource;
+use crate::typing::TypingUpdate;
+
+/// 没有其他事件时产生 Tick 事件的间隔
+pub const TICK_RATE: Duration = Duration::from_millis(100);
+
+/// 读取终端输入的线程检查停止请求的间隔
+const INPUT_POLL_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// 主循环处理的事件
+pub enum AppEvent {
+    Key(KeyEvent),
+    /// 终端窗口大小变化，重新绘制时使用新的大小
+    Resize,
+    /// 按固定间隔产生，用于刷新倒计时和让提示消息过期
+    Tick,
+    /// 后台输出任务发回的进度消息
+    Typing(TypingUpdate),
+    /// 后台高亮线程算好的详情项文本
+    Highlighted(Highlighted),
+}
+
+/// 向事件队列发送事件，可以复制后交给后台任务
+#[derive(Clone)]
+pub struct EventSender(Sender<io::Result<AppEvent>>);
+
+impl EventSender {
+    /// 发送事件，队列已经关闭时返回 false
+    pub fn send(&self, event: AppEvent) -> bool {
+        self.0.send(Ok(event)).is_ok()
+    }
+}
+
+/// 主循环的事件队列：终端输入、窗口大小变化和后台任务的消息都经过同一个通道，
+/// 一段时间内没有事件时产生 Tick 事件
+pub struct EventQueue {
+    tx: Sender<io::Result<AppEvent>>,
+    rx: Receiver<io::Result<AppEvent>>,
+    tick_rate: Duration,
+    last_tick: Instant,
+}
+
+impl EventQueue {
+    pub fn new(tick_rate: Duration) -> EventQueue {
+        let (tx, rx) = mpsc::channel();
+        EventQueue {
+            tx,
+            rx,
+            tick_rate,
+            last_tick: Instant::now(),
+        }
+    }
+
+    pub fn sender(&self) -> EventSender {
+        EventSender(self.tx.clone())
+    }
+
+    /// 启动后台线程从 source 读取终端事件并放入队列，读取出错时把错误交给主循环
+    ///
+    /// 返回的 InputReader 离开作用域时停止线程。
+    pub fn spawn_input(&self, mut source: impl EventSource + Send + 'static) -> InputReader {
+        let tx = self.tx.clone();
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = Arc::clone(&stop);
+        let handle = thread::spawn(move || {
+            while !thread_stop.load(Ordering::Relaxed) {
+                let event = match source.next_event(INPUT_POLL_TIMEOUT) {
+                    Ok(Some(Event::Key(key))) => AppEvent::Key(key),
+                    Ok(Some(Event::Resize(..))) => AppEvent::Resize,
+                    // 鼠标、焦点和粘贴事件暂不处理
+                    Ok(_) => continue,
+                    Err(e) => {
+                        let _ = tx.send(Err(e));
+                        return;
+                    }
+                };
+                if tx.send(Ok(event)).is_err() {
+                    return;
+                }
+            }
+        });
+
+        InputReader {
+            stop,
+            handle: Some(handle),
+        }
+    }
+
+    /// 等待下一个事件，距离上一个 Tick 超过 tick_rate 时返回 Tick
+    pub fn next(&mut self) -> io::Result<AppEvent> {
+        // 先检查 Tick，避免事件不断到达时 Tick 一直得不到处理
+        let timeout = self.tick_rate.saturating_sub(self.last_tick.elapsed());
+        if !timeout.is_zero() {
+            match self.rx.recv_timeout(timeout) {
+                Ok(event) => return event,
+                Err(RecvTimeoutError::Timeout) => {}
+                // 队列自己持有发送端，通道不会断开
+                Err(RecvTimeoutError::Disconnected) => unreachable!(),
+            }
+        }
+
+        self.last_tick = Instant::now();
+        Ok(AppEvent::Tick)
+    }
+}
+
+/// 读取终端输入的后台线程
+pub struct InputReader {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Drop for InputReader {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::ScriptedEvents;
+    use crossterm::event::{KeyCode, KeyModifiers};
+
+    #[test]
+    fn ticks_when_no_event_arrives() {
+        let mut queue = EventQueue::new(Duration::from_millis(5));
+        let started = Instant::now();
+
+        assert!(matches!(queue.next().unwrap(), AppEvent::Tick));
+        assert!(started.elapsed() >= Duration::from_millis(5));
+    }
+
+    #[test]
+    fn merges_input_and_worker_messages_in_order() {
+        let mut queue = EventQueue::new(Duration::from_secs(60));
+        let worker = queue.sender();
+        assert!(worker.send(AppEvent::Typing(TypingUpdate::Progress(1))));
+
+        let input = ScriptedEvents::new([
+            Event::FocusGained,
+            Event::Key(KeyEvent::new(KeyCode::Char('a'), KeyModifiers::NONE)),
+            Event::Resize(100, 30),
+        ]);
+        let _reader = queue.spawn_input(input);
+
+        assert!(matches!(queue.next().unwrap(), AppEvent::Typing(TypingUpdate::Progress(1))));
+        assert!(matches!(queue.next().unwrap(), AppEvent::Key(key) if key.code == KeyCode::Char('a')));
+        assert!(matches!(queue.next().unwrap(), AppEvent::Resize));
+        // 按键脚本结束后读取线程把错误交给主循环
+        let error = queue.next().err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
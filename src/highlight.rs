@@ -0,0 +1,328 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::OnceLock;
+use std::thread;
+
+use ratatui::style::Color;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+use syntect::util::LinesWithEndings;
+
+use crate::events::{AppEvent, EventSender};
+
+/// 高亮使用的配色，来自 syntect 自带的主题
+const THEME: &str = "base16-ocean.dark";
+
+/// 高亮后的一行：依次排列的前景色和文本
+pub type StyledLine = Vec<(Color, String)>;
+
+/// 一个详情项文本的高亮结果
+pub struct Highlighted {
+    pub location: (usize, usize),
+    /// 高亮时的语言和文本的摘要，用于判断结果是否过期
+    key: u64,
+    /// 使用的语法名称，找不到语法时为 None
+    pub syntax: Option<String>,
+    /// 每个原文行的高亮结果，没有语法时为空
+    pub lines: Vec<StyledLine>,
+}
+
+/// 交给后台线程的高亮任务
+struct Job {
+    location: (usize, usize),
+    key: u64,
+    language: Option<String>,
+    text: String,
+}
+
+/// 语法高亮：在后台线程中计算，结果通过事件队列发回并按详情项缓存
+///
+/// 第一次请求高亮时才启动后台线程，语法定义也在后台线程中加载。
+pub struct Highlighter {
+    events: EventSender,
+    jobs: Option<Sender<Job>>,
+    cache: HashMap<(usize, usize), Highlighted>,
+    /// 已经提交、还没有收到结果的任务
+    pending: HashMap<(usize, usize), u64>,
+}
+
+impl Highlighter {
+    pub fn new(events: EventSender) -> Highlighter {
+        Highlighter {
+            events,
+            jobs: None,
+            cache: HashMap::new(),
+            pending: HashMap::new(),
+        }
+    }
+
+    /// 详情项最近一次的高亮结果，文本修改后在新结果到达前仍返回旧的结果
+    pub fn get(&self, location: (usize, usize)) -> Option<&Highlighted> {
+        self.cache.get(&location)
+    }
+
+    /// 缓存中没有该语言和文本的结果，也没有正在计算时，提交给后台线程
+    pub fn request(&mut self, location: (usize, usize), language: Option<&str>, text: &str) {
+        let key = job_key(language, text);
+        if self.cache.get(&location).is_some_and(|h| h.key == key) || self.pending.get(&location) == Some(&key) {
+            return;
+        }
+
+        let jobs = self.jobs.get_or_insert_with(|| spawn_worker(self.events.clone()));
+        let job = Job {
+            location,
+            key,
+            language: language.map(str::to_string),
+            text: text.to_string(),
+        };
+        if jobs.send(job).is_ok() {
+            self.pending.insert(location, key);
+        }
+    }
+
+    /// 是否还有任务在后台计算
+    #[cfg(test)]
+    pub fn is_busy(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// 保存后台线程发回的结果
+    pub fn store(&mut self, result: Highlighted) {
+        if self.pending.get(&result.location) == Some(&result.key) {
+            self.pending.remove(&result.location);
+        }
+        self.cache.insert(result.location, result);
+    }
+}
+
+fn job_key(language: Option<&str>, text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    language.hash(&mut hasher);
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// 启动后台线程，线程在 Highlighter 释放或事件队列关闭后退出
+fn spawn_worker(events: EventSender) -> Sender<Job> {
+    let (tx, jobs) = mpsc::channel();
+    thread::spawn(move || run_worker(&jobs, &events));
+    tx
+}
+
+/// 语法定义和配色，加载比较慢，所有后台线程共用一份
+fn definitions() -> &'static (SyntaxSet, Theme) {
+    static DEFINITIONS: OnceLock<(SyntaxSet, Theme)> = OnceLock::new();
+    DEFINITIONS.get_or_init(|| {
+        let mut themes = ThemeSet::load_defaults();
+        let theme = themes.themes.remove(THEME).unwrap_or_default();
+        (SyntaxSet::load_defaults_newlines(), theme)
+    })
+}
+
+fn run_worker(jobs: &Receiver<Job>, events: &EventSender) {
+    let (syntaxes, theme) = definitions();
+
+    while let Ok(job) = jobs.recv() {
+        let mut batch = vec![job];
+        batch.extend(jobs.try_iter());
+        for (i, job) in batch.iter().enumerate() {
+            // 编辑时每次按键都会提交任务，同一个详情项只处理最新的一个
+            if batch[i + 1..].iter().any(|later| later.location == job.location) {
+                continue;
+            }
+            if !events.send(AppEvent::Highlighted(highlight(syntaxes, theme, job))) {
+                return;
+            }
+        }
+    }
+}
+
+fn highlight(syntaxes: &SyntaxSet, theme: &Theme, job: &Job) -> Highlighted {
+    let mut result = Highlighted {
+        location: job.location,
+        key: job.key,
+        syntax: None,
+        lines: Vec::new(),
+    };
+    let Some(syntax) = find_syntax(syntaxes, job.language.as_deref(), &job.text) else {
+        return result;
+    };
+
+    let mut highlighter = HighlightLines::new(syntax, theme);
+    for line in LinesWithEndings::from(&job.text) {
+        let regions = highlighter.highlight_line(line, syntaxes).unwrap_or_default();
+        let styled = regions
+            .into_iter()
+            .map(|(style, text)| {
+                let color = Color::Rgb(style.foreground.r, style.foreground.g, style.foreground.b);
+                (color, text.trim_end_matches(['\r', '\n']).to_string())
+            })
+            .filter(|(_, text)| !text.is_empty())
+            .collect();
+        result.lines.push(styled);
+    }
+    result.syntax = Some(syntax.name.clone());
+    result
+}
+
+/// 按详情项设置的语言查找语法，没有设置时按文本内容猜测
+fn find_syntax<'a>(syntaxes: &'a SyntaxSet, language: Option<&str>, text: &str) -> Option<&'a SyntaxReference> {
+    if let Some(language) = language {
+        return syntaxes.find_syntax_by_token(language);
+    }
+    syntaxes
+        .find_syntax_by_first_line(text)
+        .or_else(|| detect_language(text).and_then(|token| syntaxes.find_syntax_by_token(token)))
+}
+
+/// 常见的 shell 命令，文本第一个词是这些命令时按 shell 脚本高亮
+const SHELL_COMMANDS: &[&str] = &[
+    "cd", "ls", "echo", "cat", "grep", "sudo", "ssh", "scp", "git", "cargo", "docker", "kubectl", "curl", "wget",
+    "export", "tar", "mkdir", "rm", "cp", "mv", "chmod", "systemctl", "apt", "npm", "make", "for", "if",
+];
+
+/// SQL 语句开头的关键字
+const SQL_KEYWORDS: &[&str] = &["select", "insert", "update", "delete", "create", "alter", "drop", "with"];
+
+/// Rust 代码中常见的写法
+const RUST_HINTS: &[&str] = &["fn ", "let ", "use ", "impl ", "pub ", "struct ", "enum ", "println!", "::", "&mut "];
+
+/// 根据文本内容猜测语言，返回 syntect 的语言标记；只识别常用的 shell、SQL、Rust 和 YAML
+pub fn detect_language(text: &str) -> Option<&'static str> {
+    let lines: Vec<&str> = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+    let first = lines.first()?;
+    let word = first.split_whitespace().next()?.to_ascii_lowercase();
+
+    if SQL_KEYWORDS.contains(&word.as_str()) {
+        return Some("sql");
+    }
+    if RUST_HINTS.iter().any(|hint| text.contains(hint)) && (text.contains(';') || text.contains('{')) {
+        return Some("rs");
+    }
+    if *first == "---" || lines.iter().all(|line| is_yaml_line(line)) {
+        return Some("yaml");
+    }
+    if SHELL_COMMANDS.contains(&word.as_str()) || ["&&", "| ", "$("].iter().any(|s| text.contains(s)) {
+        return Some("bash");
+    }
+    None
+}
+
+/// `key: value`、`key:` 或 `- item` 形式的一行
+fn is_yaml_line(line: &str) -> bool {
+    if line.starts_with("- ") {
+        return true;
+    }
+    let Some((key, value)) = line.split_once(':') else {
+        return false;
+    };
+    !key.is_empty()
+        && key.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'))
+        && (value.is_empty() || value.starts_with(' '))
+}
+
+/// 取出高亮行中第 range 个字符的部分，按原来的颜色分段
+pub fn slice_styled(line: &StyledLine, range: Range<usize>) -> StyledLine {
+    let mut result = Vec::new();
+    let mut offset = 0;
+    for (color, text) in line {
+        let len = text.chars().count();
+        let start = range.start.clamp(offset, offset + len) - offset;
+        let end = range.end.clamp(offset, offset + len) - offset;
+        if start < end {
+            result.push((*color, text.chars().skip(start).take(end - start).collect()));
+        }
+        offset += len;
+    }
+    result
+}
+
+/// 高亮行拼接后是否就是 text，编辑后内容变化的行不使用旧的高亮结果
+pub fn same_text(line: &StyledLine, text: &str) -> bool {
+    let mut rest = text;
+    for (_, part) in line {
+        match rest.strip_prefix(part.as_str()) {
+            Some(tail) => rest = tail,
+            None => return false,
+        }
+    }
+    rest.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{EventQueue, TICK_RATE};
+
+    fn styled_text(line: &StyledLine) -> String {
+        line.iter().map(|(_, text)| text.as_str()).collect()
+    }
+
+    #[test]
+    fn detects_common_snippet_languages() {
+        assert_eq!(detect_language("SELECT * FROM users\nWHERE id = 1;"), Some("sql"));
+        assert_eq!(detect_language("let app = App::new();"), Some("rs"));
+        assert_eq!(detect_language("name: heillo\nversion: 1\ndeps:\n  - ratatui"), Some("yaml"));
+        assert_eq!(detect_language("ssh root@prod"), Some("bash"));
+        assert_eq!(detect_language("make build && ./run.sh"), Some("bash"));
+        assert_eq!(detect_language("Hello, world"), None);
+        assert_eq!(detect_language(""), None);
+    }
+
+    #[test]
+    fn slices_styled_line_by_characters() {
+        let line = vec![(Color::Red, "let".to_string()), (Color::White, " x = 中文".to_string())];
+        assert_eq!(
+            slice_styled(&line, 1..6),
+            vec![(Color::Red, "et".to_string()), (Color::White, " x ".to_string())]
+        );
+        assert_eq!(styled_text(&slice_styled(&line, 8..20)), "中文");
+        assert!(slice_styled(&line, 20..30).is_empty());
+        assert!(same_text(&line, "let x = 中文"));
+        assert!(!same_text(&line, "let x = 中"));
+        assert!(!same_text(&line, "let x = 中文字"));
+    }
+
+    /// 从事件队列中等待高亮结果
+    fn wait_highlighted(queue: &mut EventQueue) -> Highlighted {
+        loop {
+            if let AppEvent::Highlighted(result) = queue.next().unwrap() {
+                return result;
+            }
+        }
+    }
+
+    #[test]
+    fn highlights_in_background_and_caches_per_item() {
+        let mut queue = EventQueue::new(TICK_RATE);
+        let mut highlighter = Highlighter::new(queue.sender());
+
+        highlighter.request((0, 0), None, "fn main() {\n    println!(\"hi\");\n}");
+        highlighter.request((0, 1), Some("sql"), "select 1");
+        highlighter.request((0, 2), Some("no-such-language"), "text");
+        for _ in 0..3 {
+            let result = wait_highlighted(&mut queue);
+            highlighter.store(result);
+        }
+
+        let rust = highlighter.get((0, 0)).unwrap();
+        assert_eq!(rust.syntax.as_deref(), Some("Rust"));
+        assert_eq!(rust.lines.len(), 3);
+        assert_eq!(styled_text(&rust.lines[1]), "    println!(\"hi\");");
+        assert!(rust.lines[0].len() > 1);
+        assert_eq!(highlighter.get((0, 1)).unwrap().syntax.as_deref(), Some("SQL"));
+        assert!(highlighter.get((0, 2)).unwrap().syntax.is_none());
+
+        // 相同的文本不会重复计算
+        highlighter.request((0, 1), Some("sql"), "select 1");
+        assert!(!highlighter.is_busy());
+    }
+}
@@ -0,0 +1,110 @@
+/// 撤销/重做历史，保存操作前的状态快照
+pub struct History<T> {
+    undo: Vec<T>,
+    redo: Vec<T>,
+    limit: usize,
+}
+
+impl<T> History<T> {
+    pub fn new(limit: usize) -> History<T> {
+        History {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            limit,
+        }
+    }
+
+    /// 记录修改前的状态，新的修改会清空重做记录；limit 为 0 时不保存历史
+    pub fn record(&mut self, snapshot: T) {
+        self.redo.clear();
+        if self.limit == 0 {
+            return;
+        }
+        if self.undo.len() == self.limit {
+            self.undo.remove(0);
+        }
+        self.undo.push(snapshot);
+    }
+
+    /// 撤销：传入当前状态，返回要恢复的状态
+    pub fn undo(&mut self, current: T) -> Option<T> {
+        let snapshot = self.undo.pop()?;
+        self.redo.push(current);
+        Some(snapshot)
+    }
+
+    /// 重做：传入当前状态，返回要恢复的状态
+    pub fn redo(&mut self, current: T) -> Option<T> {
+        let snapshot = self.redo.pop()?;
+        self.undo.push(current);
+        Some(snapshot)
+    }
+
+    pub fn undo_depth(&self) -> usize {
+        self.undo.len()
+    }
+
+    pub fn redo_depth(&self) -> usize {
+        self.redo.len()
+    }
+}
+
+impl<T> Default for History<T> {
+    fn default() -> History<T> {
+        History::new(100)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undo_and_redo_walk_back_and_forth() {
+        let mut history = History::new(10);
+        history.record(1);
+        history.record(2);
+
+        assert_eq!(history.undo(3), Some(2));
+        assert_eq!(history.undo(2), Some(1));
+        assert_eq!(history.undo(1), None);
+        assert_eq!((history.undo_depth(), history.redo_depth()), (0, 2));
+
+        assert_eq!(history.redo(1), Some(2));
+        assert_eq!(history.redo(2), Some(3));
+        assert_eq!(history.redo(3), None);
+        assert_eq!((history.undo_depth(), history.redo_depth()), (2, 0));
+    }
+
+    #[test]
+    fn new_edit_clears_redo() {
+        let mut history = History::new(10);
+        history.record(1);
+        assert_eq!(history.undo(2), Some(1));
+        assert_eq!(history.redo_depth(), 1);
+
+        history.record(1);
+        assert_eq!(history.redo_depth(), 0);
+        assert_eq!(history.redo(5), None);
+    }
+
+    #[test]
+    fn keeps_only_the_latest_snapshots() {
+        let mut history = History::new(2);
+        for i in 1..=4 {
+            history.record(i);
+        }
+        assert_eq!(history.undo_depth(), 2);
+        assert_eq!(history.undo(5), Some(4));
+        assert_eq!(history.undo(4), Some(3));
+        assert_eq!(history.undo(3), None);
+    }
+
+    #[test]
+    fn zero_limit_keeps_no_history() {
+        let mut history = History::new(0);
+        history.record(1);
+        assert_eq!(history.undo_depth(), 0);
+        assert_eq!(history.undo(2), None);
+    }
+}
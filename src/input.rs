@@ -1,292 +1,424 @@
-use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
+use ratatui::backend::Backend;
+use ratatui::Terminal;
+#[cfg(test)]
+use std::collections::VecDeque;
 use std::io;
-use std::thread;
 use std::time::Duration;
-use crate::types::DetailSelection;
 use crate::app::App;
-use crate::utils;
-use ratatui::Terminal;
-use ratatui::backend::CrosstermBackend;
-
-/// 处理键盘输入事件
-pub fn handle_key_event(
-    terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>,
-    app: &mut App
-) -> io::Result<bool> {
-    let mut should_exit = false;
-
-    if let Event::Key(key) = event::read()? {
-        if key.kind == KeyEventKind::Press {
-            match key.code {
-                // 退出键处理
-                KeyCode::Char('q') => {
-                    if app.in_save_mode {
-                        app.in_save_mode = false;
-                    } else if app.in_edit_mode {
-                        app.edit_buffer.push('q');
-                    } else if app.in_detail_page {
-                        app.in_detail_page = false;
-                    } else if app.in_detail_view {
-                        app.in_detail_view = false;
-                    } else {
-                        should_exit = true;
-                    }
-                }
-                
-                // Esc键处理
-                KeyCode::Esc => {
-                    if app.in_edit_mode {
-                        app.in_edit_mode = false;
-                        app.in_save_mode = true;
-                    } else if app.in_save_mode {
-                        app.in_save_mode = false;
-                        app.in_edit_mode = true;
-                    } else if app.in_detail_page {
-                        app.in_detail_page = false;
-                    } else if app.in_detail_view {
-                        app.in_detail_view = false;
-                    } else {
-                        should_exit = true;
-                    }
-                }
-                
-                // 向下/向下移动键处理
-                KeyCode::Down | KeyCode::Char('j') => {
-                    if app.in_edit_mode {
-                        app.edit_buffer.push('j');
-                    } else {
-                        if app.in_detail_page {
-                            match app.current_detail_selection {
-                                DetailSelection::Title => app.current_detail_selection = DetailSelection::Describe,
-                                DetailSelection::Describe => app.current_detail_selection = DetailSelection::Text,
-                                DetailSelection::Text => app.current_detail_selection = DetailSelection::Title,
-                            }
-                        } else if app.in_detail_view {
-                            app.next_detail();
-                        } else {
-                            app.next();
-                        }
-                    }
-                }
-                
-                // 向上/向上移动键处理
-                KeyCode::Up | KeyCode::Char('k') => {
-                    if app.in_edit_mode {
-                        app.edit_buffer.push('k');
-                    } else {
-                        if app.in_detail_page {
-                            match app.current_detail_selection {
-                                DetailSelection::Title => app.current_detail_selection = DetailSelection::Text,
-                                DetailSelection::Describe => app.current_detail_selection = DetailSelection::Title,
-                                DetailSelection::Text => app.current_detail_selection = DetailSelection::Describe,
-                            }
-                        } else if app.in_detail_view {
-                            app.previous_detail();
-                        } else {
-                            app.previous();
-                        }
-                    }
-                }
-                
-                // 添加新项（a键）
-                KeyCode::Char('a') => {
-                    if app.in_edit_mode {
-                        app.edit_buffer.push('a');
-                    } else {
-                        app.items.push(format!("新订阅项 {}", app.items.len() + 1));
-                    }
-                }
-                
-                // 删除当前项（d键）
-                KeyCode::Char('d') => {
-                    if app.in_edit_mode {
-                        app.edit_buffer.push('d');
-                    } else if app.in_detail_page && !app.in_save_mode {
-                        // 在详情页面中，清空当前选中区块的内容
-                        if app.current_detail_index < app.detail_items.len() {
-                            match app.current_detail_selection {
-                                DetailSelection::Title => {
-                                    app.detail_items[app.current_detail_index].title.clear();
-                                },
-                                DetailSelection::Describe => {
-                                    app.detail_items[app.current_detail_index].describe.clear();
-                                },
-                                DetailSelection::Text => {
-                                    app.detail_items[app.current_detail_index].text.clear();
-                                },
-                            }
-                        }
-                    } else {
-                        if !app.items.is_empty() {
-                            let selected = app.state.selected().unwrap_or(0);
-                            app.items.remove(selected);
-                            
-                            if app.items.is_empty() {
-                                app.state.select(None);
-                            } else {
-                                let new_selected = if selected >= app.items.len() {
-                                    app.items.len() - 1
-                                } else {
-                                    selected
-                                };
-                                app.state.select(Some(new_selected));
-                            }
-                        }
-                    }
-                }
-                
-                // 刷新排序（r键）
-                KeyCode::Char('r') => {
-                    if app.in_edit_mode {
-                        app.edit_buffer.push('r');
-                    } else {
-                        app.items.sort();
-                    }
-                }
-                
-                // 切换置顶状态（t键）
-                KeyCode::Char('t') => {
-                    if app.in_edit_mode {
-                        app.edit_buffer.push('t');
-                    } else {
-                        app.toggle_pin();
-                    }
-                }
-                
-                // 回车键处理
-                KeyCode::Enter => {
-                    if app.in_edit_mode {
-                        app.edit_buffer.push('\n');
-                    } else {
-                        if !app.in_detail_view {
-                            app.in_detail_view = true;
-                            if !app.detail_items.is_empty() {
-                                app.detail_state.select(Some(0));
-                            }
-                        } else if !app.in_detail_page {
-                            if let Some(selected) = app.detail_state.selected() {
-                                app.in_detail_page = true;
-                                app.current_detail_index = selected;
-                            }
-                        }
-                    }
-                }
-                
-                // 编辑模式（v键）
-                KeyCode::Char('v') => {
-                    if app.in_edit_mode {
-                        app.edit_buffer.push('v');
-                    } else {
-                        if app.in_detail_page && !app.in_edit_mode && !app.in_save_mode {
-                            if app.current_detail_index < app.detail_items.len() {
-                                let current_item = &app.detail_items[app.current_detail_index];
-                                app.edit_buffer = match app.current_detail_selection {
-                                    DetailSelection::Title => current_item.title.clone(),
-                                    DetailSelection::Describe => current_item.describe.clone(),
-                                    DetailSelection::Text => current_item.text.clone(),
-                                };
-                                app.in_edit_mode = true;
-                            }
-                        }
-                    }
-                }
-                
-                // 模拟键盘输出（b键）
-                KeyCode::Char('b') => {
-                    if app.in_edit_mode {
-                        app.edit_buffer.push('b');
-                    } else {
-                        if app.in_detail_page && !app.detail_items.is_empty() {
-                            if app.current_detail_index < app.detail_items.len() {
-                                let text = app.detail_items[app.current_detail_index].text.clone();
-                                thread::spawn(move || {
-                                    thread::sleep(Duration::from_secs(2));
-                                    utils::simulate_keyboard_output(&text);
-                                });
-                            }
-                        } else if app.in_detail_view && !app.detail_items.is_empty() {
-                            if let Some(selected) = app.detail_state.selected() {
-                                if selected < app.detail_items.len() {
-                                    let text = app.detail_items[selected].text.clone();
-                                    thread::spawn(move || {
-                                        thread::sleep(Duration::from_secs(2));
-                                        utils::simulate_keyboard_output(&text);
-                                    });
-                                }
-                            }
-                        }
-                    }
-                }
-                
-                // 保存并前进（w键）
-                KeyCode::Char('w') => {
-                    if app.in_save_mode {
-                        if app.current_detail_index < app.detail_items.len() {
-                            let buffer = app.edit_buffer.clone();
-                            match app.current_detail_selection {
-                                DetailSelection::Title => {
-                                    app.detail_items[app.current_detail_index].title = buffer;
-                                },
-                                DetailSelection::Describe => {
-                                    app.detail_items[app.current_detail_index].describe = buffer;
-                                },
-                                DetailSelection::Text => {
-                                    app.detail_items[app.current_detail_index].text = buffer;
-                                },
-                            }
-                        }
-                        app.in_save_mode = false;
-                        app.edit_buffer.clear();
-                    } else if app.in_edit_mode {
-                        app.edit_buffer.push('w');
-                    } else if app.in_detail_page {
-                        if !app.detail_items.is_empty() {
-                            let old_index = app.current_detail_index;
-                            app.current_detail_index = (app.current_detail_index + 1) % app.detail_items.len();
-                            if old_index != app.current_detail_index {
-                                app.edit_buffer.clear();
-                            }
-                        }
-                    } else if app.in_detail_view {
-                        app.next_detail();
-                    } else {
-                        app.next();
-                    }
-                }
-                
-                // 字符输入
-                KeyCode::Char(c) => {
-                    if app.in_edit_mode {
-                        app.edit_buffer.push(c);
-                    }
-                }
-                
-                // 退格键处理
-                KeyCode::Backspace => {
-                    if app.in_edit_mode {
-                        app.edit_buffer.pop();
-                    }
-                }
-                
-                // 忽略其他按键
-                _ => {}
-            }
+use crate::keymap::Keymap;
+use crate::types::{AppState, InputEvent};
+
+/// 按当前视图的按键绑定把终端按键转换为输入事件
+pub fn translate_key(keymap: &Keymap, state: AppState, key: KeyEvent) -> InputEvent {
+    if let Some(event) = keymap.lookup(state, key) {
+        return event;
+    }
+
+    // 可以输入文本的视图中，没有绑定的字符按键作为文本输入
+    match key.code {
+        KeyCode::Char(c)
+            if accepts_text(state) && !key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+        {
+            InputEvent::CharacterInput(c)
+        }
+        _ => InputEvent::OtherKey,
+    }
+}
+
+/// 视图是否把没有绑定的字符按键作为文本输入：编辑模式、填写变量、搜索和命令面板
+pub fn accepts_text(state: AppState) -> bool {
+    matches!(
+        state,
+        AppState::EditMode | AppState::PromptMode | AppState::SearchMode | AppState::PaletteMode
+    )
+}
+
+/// 终端事件的来源，在后台线程中读取，测试时可以换成预先写好的按键序列
+pub trait EventSource {
+    /// 最多等待 timeout，超时返回 None
+    fn next_event(&mut self, timeout: Duration) -> io::Result<Option<Event>>;
+}
+
+/// 从终端读取事件
+pub struct TerminalEvents;
+
+impl EventSource for TerminalEvents {
+    fn next_event(&mut self, timeout: Duration) -> io::Result<Option<Event>> {
+        if event::poll(timeout)? {
+            event::read().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// 按顺序返回预先写好的事件，全部用完后返回 UnexpectedEof 错误结束主循环
+#[cfg(test)]
+pub struct ScriptedEvents {
+    events: VecDeque<Event>,
+}
+
+#[cfg(test)]
+impl ScriptedEvents {
+    pub fn new(events: impl IntoIterator<Item = Event>) -> ScriptedEvents {
+        ScriptedEvents {
+            events: events.into_iter().collect(),
+        }
+    }
+
+    /// 不带修饰键的按键序列
+    pub fn keys(codes: impl IntoIterator<Item = KeyCode>) -> ScriptedEvents {
+        ScriptedEvents::new(
+            codes
+                .into_iter()
+                .map(|code| Event::Key(KeyEvent::new(code, KeyModifiers::NONE))),
+        )
+    }
+}
+
+#[cfg(test)]
+impl EventSource for ScriptedEvents {
+    fn next_event(&mut self, _timeout: Duration) -> io::Result<Option<Event>> {
+        match self.events.pop_front() {
+            Some(event) => Ok(Some(event)),
+            None => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "按键脚本已经结束")),
         }
     }
-    
-    Ok(!should_exit)
 }
 
 /// 运行应用程序主循环
-pub fn run_app(terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>, app: &mut App) -> io::Result<()> {
+///
+/// 终端输入在后台线程中读取，和后台任务的消息、定时的 Tick 事件合并到同一个队列中，
+/// 每处理一个事件重新绘制一次。读取终端输入出错时返回该错误。
+pub fn run_app<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    input: impl EventSource + Send + 'static,
+) -> io::Result<()> {
+    let _reader = app.events.spawn_input(input);
     loop {
         terminal.draw(|f| crate::ui::draw(f, app))?;
 
-        let should_continue = handle_key_event(terminal, app)?;
-        if !should_continue {
+        let event = app.events.next()?;
+        if !app.handle_event(event) {
             break;
         }
     }
-    
+
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::AppEvent;
+    use ratatui::backend::TestBackend;
+
+    /// 在测试终端中运行按键脚本，脚本用完后返回最终画面
+    fn run_script(app: &mut App, script: impl IntoIterator<Item = KeyCode>) -> Terminal<TestBackend> {
+        run_events(app, ScriptedEvents::keys(script))
+    }
+
+    fn run_events(app: &mut App, events: ScriptedEvents) -> Terminal<TestBackend> {
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        match run_app(&mut terminal, app, events) {
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {}
+            result => result.unwrap(),
+        }
+        // 等待后台高亮完成，画面不受线程调度影响
+        while app.highlighter.is_busy() {
+            if let AppEvent::Highlighted(result) = app.events.next().unwrap() {
+                app.highlighter.store(result);
+            }
+        }
+
+        // TestBackend 只写入每帧变化的单元格，宽字符遮住的单元格会残留上一帧的内容，
+        // 所以在新的终端中重新绘制一次最终状态用于比较
+        draw_at(app, 80, 24)
+    }
+
+    /// 在指定大小的新终端中绘制当前状态
+    fn draw_at(app: &mut App, width: u16, height: u16) -> Terminal<TestBackend> {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|f| crate::ui::draw(f, app)).unwrap();
+        terminal
+    }
+
+    /// 终端画面的文本，每行一个字符串，宽字符占据的第二个单元格不输出
+    fn screen(terminal: &Terminal<TestBackend>) -> String {
+        let buffer = terminal.backend().buffer();
+        let mut text = String::new();
+        for y in 0..buffer.area.height {
+            let mut skip = 0;
+            for x in 0..buffer.area.width {
+                let symbol = buffer.get(x, y).symbol();
+                if skip > 0 {
+                    skip -= 1;
+                    continue;
+                }
+                text.push_str(symbol);
+                skip = unicode_width::UnicodeWidthStr::width(symbol).saturating_sub(1);
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    fn chars(text: &str) -> Vec<KeyCode> {
+        text.chars().map(KeyCode::Char).collect()
+    }
+
+    #[test]
+    fn translates_keys_by_view() {
+        use AppState::*;
+        use InputEvent as E;
+        let none = KeyModifiers::NONE;
+        let ctrl = KeyModifiers::CONTROL;
+        let alt = KeyModifiers::ALT;
+        let shift = KeyModifiers::SHIFT;
+        let cases = [
+            (MainView, KeyCode::Char('q'), none, E::Quit),
+            (MainView, KeyCode::Char('j'), none, E::Down),
+            (MainView, KeyCode::Char('a'), none, E::AddItem),
+            (MainView, KeyCode::Char('z'), ctrl, E::Undo),
+            (MainView, KeyCode::Char('z'), ctrl | shift, E::Redo),
+            (MainView, KeyCode::Char('p'), ctrl, E::Help),
+            (MainView, KeyCode::F(1), none, E::ShowHelp),
+            // 主视图不接受文本输入，没有绑定的按键都是 OtherKey
+            (MainView, KeyCode::Char('v'), none, E::OtherKey),
+            (DetailView, KeyCode::Enter, none, E::Enter),
+            (DetailView, KeyCode::Char('b'), none, E::SimulateOutput),
+            (DetailView, KeyCode::Char('c'), none, E::CopyText),
+            (DetailView, KeyCode::Char('r'), none, E::OtherKey),
+            (DetailPage, KeyCode::Char('v'), none, E::EditMode),
+            (DetailPage, KeyCode::Char('w'), none, E::NextDetail),
+            (DetailPage, KeyCode::Char('K'), shift, E::ScrollUp),
+            (DetailPage, KeyCode::Char('k'), none, E::Up),
+            (DetailPage, KeyCode::Char('z'), none, E::ToggleWrap),
+            (EditMode, KeyCode::Char('q'), none, E::CharacterInput('q')),
+            (EditMode, KeyCode::Char('Q'), shift, E::CharacterInput('Q')),
+            (EditMode, KeyCode::Char('中'), none, E::CharacterInput('中')),
+            (EditMode, KeyCode::Esc, none, E::Escape),
+            (EditMode, KeyCode::Enter, none, E::Enter),
+            (EditMode, KeyCode::Left, ctrl, E::WordLeft),
+            (EditMode, KeyCode::Char('b'), alt, E::WordLeft),
+            (EditMode, KeyCode::Char('z'), alt, E::ToggleWrap),
+            (EditMode, KeyCode::Char('z'), ctrl, E::Undo),
+            // 带 Ctrl 或 Alt 的字符不作为文本输入
+            (EditMode, KeyCode::Char('x'), ctrl, E::OtherKey),
+            (EditMode, KeyCode::Char('x'), alt, E::OtherKey),
+            (SaveMode, KeyCode::Char('w'), none, E::SaveSelection),
+            (SaveMode, KeyCode::Char('q'), none, E::DiscardEdit),
+            (SaveMode, KeyCode::Char('x'), none, E::OtherKey),
+            (PromptMode, KeyCode::Tab, none, E::Down),
+            (PromptMode, KeyCode::Char('j'), none, E::CharacterInput('j')),
+            (SearchMode, KeyCode::Char('k'), ctrl, E::Up),
+            (SearchMode, KeyCode::Char('k'), none, E::CharacterInput('k')),
+            (SearchMode, KeyCode::Char('q'), none, E::CharacterInput('q')),
+            (PaletteMode, KeyCode::Char('p'), ctrl, E::Escape),
+            (PaletteMode, KeyCode::Char('p'), none, E::CharacterInput('p')),
+            (HelpMode, KeyCode::Char('q'), none, E::Escape),
+            (HelpMode, KeyCode::Char('G'), shift, E::End),
+            (HelpMode, KeyCode::Char(' '), none, E::PageDown),
+            (HelpMode, KeyCode::Char('a'), none, E::OtherKey),
+        ];
+
+        let keymap = Keymap::default();
+        for (state, code, modifiers, expected) in cases {
+            let event = translate_key(&keymap, state, KeyEvent::new(code, modifiers));
+            assert_eq!(event, expected, "{:?} 中的 {:?} + {:?}", state, code, modifiers);
+        }
+    }
+
+    #[test]
+    fn quit_key_ends_the_loop() {
+        let mut app = App::new();
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        let events = ScriptedEvents::keys([KeyCode::Down, KeyCode::Char('q'), KeyCode::Down]);
+
+        run_app(&mut terminal, &mut app, events).unwrap();
+        // 退出后剩下的按键不会被处理
+        assert_eq!(app.state.selected(), Some(1));
+    }
+
+    #[test]
+    fn typing_progress_arrives_through_the_queue() {
+        let mut app = App::new();
+        app.output_sink = crate::output::SinkKind::Recorder;
+        app.typing_settings.start_delay_ms = 0;
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        let events = ScriptedEvents::keys([KeyCode::Enter, KeyCode::Char('b')]);
+
+        // 按键脚本结束后主循环返回错误，输出任务的消息仍留在队列中
+        assert!(run_app(&mut terminal, &mut app, events).is_err());
+        while app.typing.is_some() {
+            let event = app.events.next().unwrap();
+            app.handle_event(event);
+        }
+        assert_eq!(app.status_message.as_deref(), Some("输出完成"));
+        assert!(!app.recorder.take().is_empty());
+    }
+
+    #[test]
+    fn edits_and_saves_through_the_loop() {
+        let mut app = App::new();
+        let mut script = vec![KeyCode::Enter, KeyCode::Enter, KeyCode::Char('v'), KeyCode::End];
+        script.extend(chars("!"));
+        script.extend([KeyCode::Esc, KeyCode::Char('w')]);
+        run_script(&mut app, script);
+
+        assert_eq!(app.app_state, AppState::DetailPage);
+        assert_eq!(app.items[0].details[0].title, "学习Rust!");
+    }
+
+    #[test]
+    fn text_pane_is_highlighted_by_detected_language() {
+        let mut app = App::new();
+        let terminal = run_script(&mut app, [KeyCode::Enter, KeyCode::Enter]);
+        let syntax = app.highlighter.get((0, 0)).and_then(|h| h.syntax.as_deref());
+        assert_eq!(syntax, Some("Rust"));
+
+        let buffer = terminal.backend().buffer();
+        let y = screen(&terminal).lines().position(|line| line.contains("println!")).unwrap() as u16;
+        let x = (0..buffer.area.width).find(|&x| buffer.get(x, y).symbol() == "p").unwrap();
+        assert!(matches!(buffer.get(x, y).fg, ratatui::style::Color::Rgb(..)));
+    }
+
+    #[test]
+    fn language_field_overrides_detection() {
+        let mut app = App::new();
+        let mut script = vec![KeyCode::Enter, KeyCode::Enter, KeyCode::Down, KeyCode::Char('v')];
+        script.extend(chars(" sql "));
+        script.extend([KeyCode::Esc, KeyCode::Char('w')]);
+        run_script(&mut app, script);
+
+        assert_eq!(app.items[0].details[0].language.as_deref(), Some("sql"));
+        let syntax = app.highlighter.get((0, 0)).and_then(|h| h.syntax.as_deref());
+        assert_eq!(syntax, Some("SQL"));
+    }
+
+    #[test]
+    fn snapshot_main_view() {
+        let terminal = run_script(&mut App::new(), [KeyCode::Down]);
+        insta::assert_snapshot!(screen(&terminal));
+    }
+
+    #[test]
+    fn snapshot_detail_view() {
+        let terminal = run_script(&mut App::new(), [KeyCode::Enter]);
+        insta::assert_snapshot!(screen(&terminal));
+    }
+
+    #[test]
+    fn snapshot_detail_page() {
+        let terminal = run_script(&mut App::new(), [KeyCode::Enter, KeyCode::Enter, KeyCode::Down]);
+        insta::assert_snapshot!(screen(&terminal));
+    }
+
+    #[test]
+    fn snapshot_edit_mode() {
+        let mut script = vec![KeyCode::Enter, KeyCode::Enter, KeyCode::Char('v'), KeyCode::End];
+        script.extend(chars(" 入门"));
+        let terminal = run_script(&mut App::new(), script);
+        insta::assert_snapshot!(screen(&terminal));
+    }
+
+    #[test]
+    fn snapshot_save_mode() {
+        let script = [KeyCode::Enter, KeyCode::Enter, KeyCode::Char('v'), KeyCode::Esc];
+        let terminal = run_script(&mut App::new(), script);
+        insta::assert_snapshot!(screen(&terminal));
+    }
+
+    #[test]
+    fn snapshot_prompt_mode() {
+        let mut app = App::new();
+        app.items[0].details[0].text = "ssh ${user:root}@${host}".to_string();
+        let mut script = vec![KeyCode::Enter, KeyCode::Char('c'), KeyCode::Down];
+        script.extend(chars("db1"));
+        let terminal = run_script(&mut app, script);
+        insta::assert_snapshot!(screen(&terminal));
+    }
+
+    #[test]
+    fn snapshot_search_mode() {
+        let mut script = vec![KeyCode::Char('/')];
+        script.extend(chars("rat"));
+        let terminal = run_script(&mut App::new(), script);
+        insta::assert_snapshot!(screen(&terminal));
+    }
+
+    #[test]
+    fn snapshot_palette_mode() {
+        let events = ScriptedEvents::new([
+            Event::Key(KeyEvent::new(KeyCode::Char('p'), KeyModifiers::CONTROL)),
+            Event::Key(KeyEvent::new(KeyCode::Char('d'), KeyModifiers::NONE)),
+        ]);
+        let terminal = run_events(&mut App::new(), events);
+        insta::assert_snapshot!(screen(&terminal));
+    }
+
+    #[test]
+    fn snapshot_help_mode() {
+        let terminal = run_script(&mut App::new(), [KeyCode::Char('?'), KeyCode::Down]);
+        insta::assert_snapshot!(screen(&terminal));
+    }
+
+    #[test]
+    fn snapshot_compact_detail_page() {
+        let mut app = App::new();
+        run_script(&mut app, [KeyCode::Enter, KeyCode::Enter]);
+        insta::assert_snapshot!(screen(&draw_at(&mut app, 60, 16)));
+    }
+
+    #[test]
+    fn snapshot_terminal_too_small() {
+        let mut app = App::new();
+        insta::assert_snapshot!(screen(&draw_at(&mut app, 30, 8)));
+    }
+
+    #[test]
+    fn every_view_draws_at_any_size() {
+        let scripts = [
+            vec![KeyCode::Enter, KeyCode::Enter, KeyCode::Char('v')],
+            vec![KeyCode::Char('/'), KeyCode::Char('r')],
+            vec![KeyCode::Char('?')],
+        ];
+        for script in scripts {
+            let mut app = App::new();
+            run_script(&mut app, script);
+            for (width, height) in [(1, 1), (39, 30), (40, 12), (45, 19), (200, 60)] {
+                draw_at(&mut app, width, height);
+            }
+        }
+    }
+
+    /// 一段比文本区块更长更宽的文本
+    fn long_text() -> String {
+        (1..=30)
+            .map(|i| format!("echo line {} {}", i, "-".repeat(i * 3)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn snapshot_scrolled_text_pane() {
+        let mut app = App::new();
+        app.items[0].details[0].text = long_text();
+        let mut script = vec![KeyCode::Enter, KeyCode::Enter, KeyCode::PageDown];
+        script.extend([KeyCode::Char('L'), KeyCode::Char('L'), KeyCode::Char('J')]);
+        let terminal = run_script(&mut app, script);
+        insta::assert_snapshot!(screen(&terminal));
+    }
+
+    #[test]
+    fn snapshot_wrapped_text_pane_follows_cursor() {
+        let mut app = App::new();
+        app.items[0].details[0].text = long_text();
+        let mut script = vec![KeyCode::Enter, KeyCode::Enter, KeyCode::Char('z'), KeyCode::Down, KeyCode::Down];
+        script.extend([KeyCode::Char('v'), KeyCode::PageDown, KeyCode::PageDown, KeyCode::End]);
+        let terminal = run_script(&mut app, script);
+        insta::assert_snapshot!(screen(&terminal));
+    }
+}
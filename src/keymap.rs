@@ -0,0 +1,559 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::types::{AppState, InputEvent};
+
+/// 配置文件中的按键绑定：视图名 -> 动作名 -> 按键列表
+pub type KeymapConfig = BTreeMap<String, BTreeMap<String, Vec<String>>>;
+
+/// 所有视图，按帮助信息中的显示顺序排列
+pub const VIEWS: [AppState; 9] = [
+    AppState::MainView,
+    AppState::DetailView,
+    AppState::DetailPage,
+    AppState::EditMode,
+    AppState::SaveMode,
+    AppState::PromptMode,
+    AppState::SearchMode,
+    AppState::PaletteMode,
+    AppState::HelpMode,
+];
+
+/// 默认按键绑定，与配置文件中的写法相同
+const DEFAULT_BINDINGS: &[(AppState, InputEvent, &[&str])] = &[
+    (AppState::MainView, InputEvent::Quit, &["q", "esc"]),
+    (AppState::MainView, InputEvent::Up, &["up", "k"]),
+    (AppState::MainView, InputEvent::Down, &["down", "j", "w"]),
+    (AppState::MainView, InputEvent::Enter, &["enter"]),
+    (AppState::MainView, InputEvent::AddItem, &["a"]),
+    (AppState::MainView, InputEvent::DeleteItem, &["d"]),
+    (AppState::MainView, InputEvent::Refresh, &["r"]),
+    (AppState::MainView, InputEvent::Search, &["/"]),
+    (AppState::MainView, InputEvent::TogglePinned, &["t"]),
+    (AppState::MainView, InputEvent::CancelOutput, &["x", "ctrl+c"]),
+    (AppState::MainView, InputEvent::Undo, &["ctrl+z"]),
+    (AppState::MainView, InputEvent::Redo, &["ctrl+y", "ctrl+shift+z"]),
+    (AppState::MainView, InputEvent::Help, &["ctrl+p"]),
+    (AppState::MainView, InputEvent::ShowHelp, &["?", "f1"]),
+    (AppState::DetailView, InputEvent::Quit, &["q", "esc"]),
+    (AppState::DetailView, InputEvent::Up, &["up", "k"]),
+    (AppState::DetailView, InputEvent::Down, &["down", "j", "w"]),
+    (AppState::DetailView, InputEvent::Enter, &["enter"]),
+    (AppState::DetailView, InputEvent::AddItem, &["a"]),
+    (AppState::DetailView, InputEvent::DeleteItem, &["d"]),
+    (AppState::DetailView, InputEvent::SimulateOutput, &["b"]),
+    (AppState::DetailView, InputEvent::CopyText, &["c"]),
+    (AppState::DetailView, InputEvent::Search, &["/"]),
+    (AppState::DetailView, InputEvent::TogglePinned, &["t"]),
+    (AppState::DetailView, InputEvent::CancelOutput, &["x", "ctrl+c"]),
+    (AppState::DetailView, InputEvent::Undo, &["ctrl+z"]),
+    (AppState::DetailView, InputEvent::Redo, &["ctrl+y", "ctrl+shift+z"]),
+    (AppState::DetailView, InputEvent::Help, &["ctrl+p"]),
+    (AppState::DetailView, InputEvent::ShowHelp, &["?", "f1"]),
+    (AppState::DetailPage, InputEvent::Quit, &["q", "esc"]),
+    (AppState::DetailPage, InputEvent::Up, &["up", "k"]),
+    (AppState::DetailPage, InputEvent::Down, &["down", "j"]),
+    (AppState::DetailPage, InputEvent::EditMode, &["v"]),
+    (AppState::DetailPage, InputEvent::DeleteItem, &["d"]),
+    (AppState::DetailPage, InputEvent::SimulateOutput, &["b"]),
+    (AppState::DetailPage, InputEvent::CopyText, &["c"]),
+    (AppState::DetailPage, InputEvent::NextDetail, &["w"]),
+    (AppState::DetailPage, InputEvent::TogglePreview, &["p"]),
+    (AppState::DetailPage, InputEvent::ScrollUp, &["K", "ctrl+up"]),
+    (AppState::DetailPage, InputEvent::ScrollDown, &["J", "ctrl+down"]),
+    (AppState::DetailPage, InputEvent::ScrollLeft, &["H", "ctrl+left"]),
+    (AppState::DetailPage, InputEvent::ScrollRight, &["L", "ctrl+right"]),
+    (AppState::DetailPage, InputEvent::PageUp, &["pageup"]),
+    (AppState::DetailPage, InputEvent::PageDown, &["pagedown"]),
+    (AppState::DetailPage, InputEvent::ToggleWrap, &["z"]),
+    (AppState::DetailPage, InputEvent::ToggleLineNumbers, &["n"]),
+    (AppState::DetailPage, InputEvent::Search, &["/"]),
+    (AppState::DetailPage, InputEvent::TogglePinned, &["t"]),
+    (AppState::DetailPage, InputEvent::CancelOutput, &["x", "ctrl+c"]),
+    (AppState::DetailPage, InputEvent::Undo, &["ctrl+z"]),
+    (AppState::DetailPage, InputEvent::Redo, &["ctrl+y", "ctrl+shift+z"]),
+    (AppState::DetailPage, InputEvent::Help, &["ctrl+p"]),
+    (AppState::DetailPage, InputEvent::ShowHelp, &["?", "f1"]),
+    (AppState::EditMode, InputEvent::Escape, &["esc"]),
+    (AppState::EditMode, InputEvent::Enter, &["enter"]),
+    (AppState::EditMode, InputEvent::Backspace, &["backspace"]),
+    (AppState::EditMode, InputEvent::Delete, &["delete"]),
+    (AppState::EditMode, InputEvent::Up, &["up"]),
+    (AppState::EditMode, InputEvent::Down, &["down"]),
+    (AppState::EditMode, InputEvent::Left, &["left"]),
+    (AppState::EditMode, InputEvent::Right, &["right"]),
+    (AppState::EditMode, InputEvent::WordLeft, &["ctrl+left", "alt+b"]),
+    (AppState::EditMode, InputEvent::WordRight, &["ctrl+right", "alt+f"]),
+    (AppState::EditMode, InputEvent::Home, &["home"]),
+    (AppState::EditMode, InputEvent::End, &["end"]),
+    (AppState::EditMode, InputEvent::PageUp, &["pageup"]),
+    (AppState::EditMode, InputEvent::PageDown, &["pagedown"]),
+    (AppState::EditMode, InputEvent::ToggleWrap, &["alt+z"]),
+    (AppState::EditMode, InputEvent::ToggleLineNumbers, &["alt+n"]),
+    (AppState::EditMode, InputEvent::Undo, &["ctrl+z"]),
+    (AppState::EditMode, InputEvent::Redo, &["ctrl+y", "ctrl+shift+z"]),
+    (AppState::EditMode, InputEvent::Help, &["ctrl+p"]),
+    (AppState::EditMode, InputEvent::ShowHelp, &["f1"]),
+    (AppState::SaveMode, InputEvent::DiscardEdit, &["q"]),
+    (AppState::SaveMode, InputEvent::SaveSelection, &["w"]),
+    (AppState::SaveMode, InputEvent::Escape, &["esc"]),
+    (AppState::SaveMode, InputEvent::Help, &["ctrl+p"]),
+    (AppState::SaveMode, InputEvent::ShowHelp, &["?", "f1"]),
+    (AppState::PromptMode, InputEvent::Enter, &["enter"]),
+    (AppState::PromptMode, InputEvent::Escape, &["esc"]),
+    (AppState::PromptMode, InputEvent::Up, &["up"]),
+    (AppState::PromptMode, InputEvent::Down, &["down", "tab"]),
+    (AppState::PromptMode, InputEvent::Left, &["left"]),
+    (AppState::PromptMode, InputEvent::Right, &["right"]),
+    (AppState::PromptMode, InputEvent::WordLeft, &["ctrl+left", "alt+b"]),
+    (AppState::PromptMode, InputEvent::WordRight, &["ctrl+right", "alt+f"]),
+    (AppState::PromptMode, InputEvent::Home, &["home"]),
+    (AppState::PromptMode, InputEvent::End, &["end"]),
+    (AppState::PromptMode, InputEvent::Backspace, &["backspace"]),
+    (AppState::PromptMode, InputEvent::Delete, &["delete"]),
+    (AppState::SearchMode, InputEvent::Enter, &["enter"]),
+    (AppState::SearchMode, InputEvent::Escape, &["esc"]),
+    (AppState::SearchMode, InputEvent::Up, &["up", "ctrl+k"]),
+    (AppState::SearchMode, InputEvent::Down, &["down", "ctrl+j"]),
+    (AppState::SearchMode, InputEvent::Left, &["left"]),
+    (AppState::SearchMode, InputEvent::Right, &["right"]),
+    (AppState::SearchMode, InputEvent::Home, &["home"]),
+    (AppState::SearchMode, InputEvent::End, &["end"]),
+    (AppState::SearchMode, InputEvent::Backspace, &["backspace"]),
+    (AppState::SearchMode, InputEvent::Delete, &["delete"]),
+    (AppState::PaletteMode, InputEvent::Enter, &["enter"]),
+    (AppState::PaletteMode, InputEvent::Escape, &["esc", "ctrl+p"]),
+    (AppState::PaletteMode, InputEvent::Up, &["up", "ctrl+k"]),
+    (AppState::PaletteMode, InputEvent::Down, &["down", "ctrl+j"]),
+    (AppState::PaletteMode, InputEvent::Left, &["left"]),
+    (AppState::PaletteMode, InputEvent::Right, &["right"]),
+    (AppState::PaletteMode, InputEvent::Home, &["home"]),
+    (AppState::PaletteMode, InputEvent::End, &["end"]),
+    (AppState::PaletteMode, InputEvent::Backspace, &["backspace"]),
+    (AppState::PaletteMode, InputEvent::Delete, &["delete"]),
+    (AppState::HelpMode, InputEvent::Escape, &["esc", "q", "?", "f1"]),
+    (AppState::HelpMode, InputEvent::Up, &["up", "k"]),
+    (AppState::HelpMode, InputEvent::Down, &["down", "j"]),
+    (AppState::HelpMode, InputEvent::PageUp, &["pageup"]),
+    (AppState::HelpMode, InputEvent::PageDown, &["pagedown", "space"]),
+    (AppState::HelpMode, InputEvent::Home, &["home", "g"]),
+    (AppState::HelpMode, InputEvent::End, &["end", "G"]),
+];
+
+/// 一个按键组合，例如 `ctrl+s`
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> KeyChord {
+        let shift = modifiers.contains(KeyModifiers::SHIFT);
+        let (code, modifiers) = match code {
+            // 有的终端按住 Shift 时仍报告小写字母，换成大写字母，使 ctrl+shift+z 与 ctrl+z 不同
+            KeyCode::Char(c) if shift && c.is_ascii_lowercase() => {
+                (KeyCode::Char(c.to_ascii_uppercase()), modifiers - KeyModifiers::SHIFT)
+            }
+            // 无法确定大写形式的字符保留 Shift
+            KeyCode::Char(c) if c.is_lowercase() => (code, modifiers),
+            // 其余字符本身已经是按下 Shift 后的结果，忽略 Shift 以便与终端事件一致
+            KeyCode::Char(_) => (code, modifiers - KeyModifiers::SHIFT),
+            _ => (code, modifiers),
+        };
+        KeyChord { code, modifiers }
+    }
+
+    /// 解析配置文件中的按键写法，例如 `q`、`ctrl+s`、`alt+enter`、`shift+a`
+    pub fn parse(text: &str) -> Result<KeyChord, String> {
+        let parts: Vec<&str> = text.split('+').collect();
+        // "+" 本身以及 "ctrl++" 这样的写法中，最后一段为空表示加号键
+        let (key, mods) = match parts.split_last() {
+            Some((last, rest)) if last.is_empty() && !rest.is_empty() => {
+                ("+", &rest[..rest.len() - 1])
+            }
+            Some((last, rest)) => (*last, rest),
+            None => return Err(format!("无效的按键: {:?}", text)),
+        };
+
+        let mut modifiers = KeyModifiers::NONE;
+        for m in mods {
+            modifiers |= match m.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "meta" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(format!("无效的修饰键 {:?}（在 {:?} 中）", m, text)),
+            };
+        }
+
+        let code = parse_key_code(key).ok_or_else(|| format!("无效的按键: {:?}", text))?;
+        Ok(KeyChord::new(code, modifiers))
+    }
+}
+
+impl From<KeyEvent> for KeyChord {
+    fn from(key: KeyEvent) -> KeyChord {
+        KeyChord::new(key.code, key.modifiers)
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "Alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "Shift+")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::Up => write!(f, "↑"),
+            KeyCode::Down => write!(f, "↓"),
+            KeyCode::Left => write!(f, "←"),
+            KeyCode::Right => write!(f, "→"),
+            KeyCode::Esc => write!(f, "Esc"),
+            KeyCode::Enter => write!(f, "Enter"),
+            KeyCode::Backspace => write!(f, "Backspace"),
+            KeyCode::Tab => write!(f, "Tab"),
+            KeyCode::Delete => write!(f, "Del"),
+            KeyCode::Home => write!(f, "Home"),
+            KeyCode::End => write!(f, "End"),
+            KeyCode::PageUp => write!(f, "PgUp"),
+            KeyCode::PageDown => write!(f, "PgDn"),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            _ => write!(f, "?"),
+        }
+    }
+}
+
+fn parse_key_code(key: &str) -> Option<KeyCode> {
+    let mut chars = key.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(KeyCode::Char(c));
+    }
+
+    let code = match key.to_ascii_lowercase().as_str() {
+        "space" => KeyCode::Char(' '),
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "esc" | "escape" => KeyCode::Esc,
+        "enter" | "return" => KeyCode::Enter,
+        "backspace" => KeyCode::Backspace,
+        "tab" => KeyCode::Tab,
+        "delete" | "del" => KeyCode::Delete,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" | "pgup" => KeyCode::PageUp,
+        "pagedown" | "pgdn" => KeyCode::PageDown,
+        other => {
+            let n = other.strip_prefix('f')?.parse().ok()?;
+            KeyCode::F(n)
+        }
+    };
+    Some(code)
+}
+
+/// 一个动作及其绑定的按键
+pub struct Binding {
+    pub event: InputEvent,
+    pub keys: Vec<KeyChord>,
+}
+
+/// 每个视图中按键到输入事件的映射
+pub struct Keymap {
+    views: HashMap<AppState, Vec<Binding>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Keymap {
+        let mut views: HashMap<AppState, Vec<Binding>> = HashMap::new();
+        for (state, event, keys) in DEFAULT_BINDINGS {
+            let keys = keys
+                .iter()
+                .map(|k| KeyChord::parse(k).expect("默认按键绑定无效"))
+                .collect();
+            views.entry(*state).or_default().push(Binding { event: *event, keys });
+        }
+        Keymap { views }
+    }
+}
+
+impl Keymap {
+    /// 在默认绑定的基础上应用配置文件中的设置，配置中出现的动作会替换该动作的默认按键
+    pub fn from_config(config: &KeymapConfig) -> io::Result<Keymap> {
+        let mut keymap = Keymap::default();
+
+        for (view_name, actions) in config {
+            let state = view_from_name(view_name)
+                .ok_or_else(|| invalid_config(format!("未知的视图: {}", view_name)))?;
+            let bindings = keymap.views.entry(state).or_default();
+
+            for (action_name, keys) in actions {
+                let event = action_from_name(action_name)
+                    .ok_or_else(|| invalid_config(format!("未知的动作: {}", action_name)))?;
+                let keys = keys
+                    .iter()
+                    .map(|k| KeyChord::parse(k))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(invalid_config)?;
+
+                match bindings.iter_mut().find(|b| b.event == event) {
+                    Some(binding) => binding.keys = keys,
+                    None => bindings.push(Binding { event, keys }),
+                }
+            }
+        }
+
+        Ok(keymap)
+    }
+
+    /// 查找按键在指定视图中对应的输入事件
+    pub fn lookup(&self, state: AppState, key: KeyEvent) -> Option<InputEvent> {
+        let chord = KeyChord::from(key);
+        self.bindings(state)
+            .iter()
+            .find(|b| b.keys.contains(&chord))
+            .map(|b| b.event)
+    }
+
+    /// 指定视图中的全部绑定，按配置顺序排列
+    pub fn bindings(&self, state: AppState) -> &[Binding] {
+        self.views.get(&state).map(|b| b.as_slice()).unwrap_or(&[])
+    }
+}
+
+/// 视图在配置文件中的名称
+pub fn view_name(state: AppState) -> &'static str {
+    match state {
+        AppState::MainView => "main_view",
+        AppState::DetailView => "detail_view",
+        AppState::DetailPage => "detail_page",
+        AppState::EditMode => "edit_mode",
+        AppState::SaveMode => "save_mode",
+        AppState::PromptMode => "prompt_mode",
+        AppState::SearchMode => "search_mode",
+        AppState::PaletteMode => "palette_mode",
+        AppState::HelpMode => "help_mode",
+    }
+}
+
+/// 视图在帮助信息中的名称
+pub fn view_title(state: AppState) -> &'static str {
+    match state {
+        AppState::MainView => "主视图",
+        AppState::DetailView => "详情视图",
+        AppState::DetailPage => "详情页面",
+        AppState::EditMode => "编辑模式",
+        AppState::SaveMode => "保存模式",
+        AppState::PromptMode => "填写模板变量",
+        AppState::SearchMode => "搜索",
+        AppState::PaletteMode => "命令面板",
+        AppState::HelpMode => "帮助",
+    }
+}
+
+fn view_from_name(name: &str) -> Option<AppState> {
+    VIEWS.into_iter().find(|state| view_name(*state) == name)
+}
+
+/// 动作在帮助信息中的说明
+pub fn action_description(state: AppState, event: InputEvent) -> &'static str {
+    match (state, event) {
+        (AppState::MainView, InputEvent::Quit) => "退出程序",
+        (AppState::DetailView, InputEvent::Quit) => "返回主列表",
+        (AppState::DetailPage, InputEvent::Quit) => "返回详情列表",
+        (_, InputEvent::Quit) => "返回",
+        (_, InputEvent::Help) => "命令面板",
+        (_, InputEvent::TogglePinned) => "窗口置顶",
+        (AppState::DetailView, InputEvent::AddItem) => "添加详情项",
+        (_, InputEvent::AddItem) => "添加订阅",
+        (AppState::DetailView, InputEvent::DeleteItem) => "删除详情项",
+        (AppState::DetailPage, InputEvent::DeleteItem) => "清空选中项内容",
+        (_, InputEvent::DeleteItem) => "删除订阅",
+        (_, InputEvent::Refresh) => "刷新列表",
+        (AppState::MainView, InputEvent::Enter) => "选择项目",
+        (AppState::DetailView, InputEvent::Enter) => "查看项目详情",
+        (AppState::EditMode, InputEvent::Enter) => "换行",
+        (AppState::PromptMode, InputEvent::Enter) => "确认并输出",
+        (AppState::SearchMode, InputEvent::Enter) => "打开选中的结果",
+        (AppState::PaletteMode, InputEvent::Enter) => "执行选中的动作",
+        (_, InputEvent::Enter) => "确认",
+        (AppState::EditMode, InputEvent::Escape) => "完成编辑",
+        (AppState::SaveMode, InputEvent::Escape) => "返回编辑模式",
+        (AppState::PromptMode, InputEvent::Escape) => "取消输出",
+        (AppState::SearchMode, InputEvent::Escape) => "取消搜索",
+        (AppState::PaletteMode, InputEvent::Escape) => "关闭命令面板",
+        (AppState::HelpMode, InputEvent::Escape) => "关闭帮助",
+        (_, InputEvent::Escape) => "返回",
+        (AppState::DetailPage, InputEvent::Up) => "上一个选择项",
+        (AppState::DetailPage, InputEvent::Down) => "下一个选择项",
+        (AppState::EditMode, InputEvent::Up) => "光标上移",
+        (AppState::EditMode, InputEvent::Down) => "光标下移",
+        (AppState::EditMode, InputEvent::Left) => "光标左移",
+        (AppState::EditMode, InputEvent::Right) => "光标右移",
+        (AppState::PromptMode, InputEvent::Up) => "上一个变量",
+        (AppState::PromptMode, InputEvent::Down) => "下一个变量",
+        (AppState::PromptMode, InputEvent::Left) => "光标左移",
+        (AppState::PromptMode, InputEvent::Right) => "光标右移",
+        (AppState::SearchMode, InputEvent::Up) => "上一个结果",
+        (AppState::SearchMode, InputEvent::Down) => "下一个结果",
+        (AppState::PaletteMode, InputEvent::Up) => "上一个动作",
+        (AppState::PaletteMode, InputEvent::Down) => "下一个动作",
+        (AppState::HelpMode, InputEvent::Up) => "向上滚动",
+        (AppState::HelpMode, InputEvent::Down) => "向下滚动",
+        (AppState::HelpMode, InputEvent::Home) => "回到顶部",
+        (AppState::HelpMode, InputEvent::End) => "跳到底部",
+        (AppState::SearchMode | AppState::PaletteMode, InputEvent::Left) => "光标左移",
+        (AppState::SearchMode | AppState::PaletteMode, InputEvent::Right) => "光标右移",
+        (_, InputEvent::Up) => "上移",
+        (_, InputEvent::Down) => "下移",
+        (_, InputEvent::Left) => "左移",
+        (_, InputEvent::Right) => "右移",
+        (_, InputEvent::Home) => "行首",
+        (_, InputEvent::End) => "行尾",
+        (AppState::DetailPage, InputEvent::PageUp) => "文本向上翻页",
+        (AppState::DetailPage, InputEvent::PageDown) => "文本向下翻页",
+        (_, InputEvent::PageUp) => "向上翻页",
+        (_, InputEvent::PageDown) => "向下翻页",
+        (_, InputEvent::WordLeft) => "上一个单词",
+        (_, InputEvent::WordRight) => "下一个单词",
+        (_, InputEvent::Delete) => "删除光标后的字符",
+        (_, InputEvent::EditMode) => "编辑选中项",
+        (_, InputEvent::SaveSelection) => "保存并退出",
+        (_, InputEvent::DiscardEdit) => "不保存退出",
+        (_, InputEvent::SimulateOutput) => "模拟键盘输出",
+        (_, InputEvent::CancelOutput) => "取消输出",
+        (_, InputEvent::CopyText) => "复制到剪贴板",
+        (_, InputEvent::NextDetail) => "下一个详情项",
+        (_, InputEvent::TogglePreview) => "预览展开后的文本",
+        (_, InputEvent::Search) => "搜索",
+        (_, InputEvent::ShowHelp) => "显示全部按键",
+        (_, InputEvent::ScrollUp) => "文本向上滚动",
+        (_, InputEvent::ScrollDown) => "文本向下滚动",
+        (_, InputEvent::ScrollLeft) => "文本向左滚动",
+        (_, InputEvent::ScrollRight) => "文本向右滚动",
+        (_, InputEvent::ToggleWrap) => "切换自动换行",
+        (_, InputEvent::ToggleLineNumbers) => "切换行号",
+        (_, InputEvent::Backspace) => "删除光标前的字符",
+        (_, InputEvent::Undo) => "撤销",
+        (_, InputEvent::Redo) => "重做",
+        (_, InputEvent::CharacterInput(_)) => "输入文本",
+        (_, InputEvent::OtherKey) => "",
+    }
+}
+
+/// 可以绑定按键的动作及其在配置文件中的名称
+const ACTION_NAMES: &[(InputEvent, &str)] = &[
+    (InputEvent::Quit, "quit"),
+    (InputEvent::Help, "help"),
+    (InputEvent::TogglePinned, "toggle_pinned"),
+    (InputEvent::AddItem, "add_item"),
+    (InputEvent::DeleteItem, "delete_item"),
+    (InputEvent::Refresh, "refresh"),
+    (InputEvent::Enter, "enter"),
+    (InputEvent::Escape, "escape"),
+    (InputEvent::Up, "up"),
+    (InputEvent::Down, "down"),
+    (InputEvent::Left, "left"),
+    (InputEvent::Right, "right"),
+    (InputEvent::Home, "home"),
+    (InputEvent::End, "end"),
+    (InputEvent::PageUp, "page_up"),
+    (InputEvent::PageDown, "page_down"),
+    (InputEvent::WordLeft, "word_left"),
+    (InputEvent::WordRight, "word_right"),
+    (InputEvent::Delete, "delete"),
+    (InputEvent::EditMode, "edit_mode"),
+    (InputEvent::SaveSelection, "save_selection"),
+    (InputEvent::DiscardEdit, "discard_edit"),
+    (InputEvent::SimulateOutput, "simulate_output"),
+    (InputEvent::CancelOutput, "cancel_output"),
+    (InputEvent::CopyText, "copy_text"),
+    (InputEvent::NextDetail, "next_detail"),
+    (InputEvent::TogglePreview, "toggle_preview"),
+    (InputEvent::Search, "search"),
+    (InputEvent::ShowHelp, "show_help"),
+    (InputEvent::ScrollUp, "scroll_up"),
+    (InputEvent::ScrollDown, "scroll_down"),
+    (InputEvent::ScrollLeft, "scroll_left"),
+    (InputEvent::ScrollRight, "scroll_right"),
+    (InputEvent::ToggleWrap, "toggle_wrap"),
+    (InputEvent::ToggleLineNumbers, "toggle_line_numbers"),
+    (InputEvent::Backspace, "backspace"),
+    (InputEvent::Undo, "undo"),
+    (InputEvent::Redo, "redo"),
+];
+
+/// 动作在配置文件中的名称
+pub fn action_name(event: InputEvent) -> Option<&'static str> {
+    ACTION_NAMES
+        .iter()
+        .find(|(e, _)| *e == event)
+        .map(|(_, name)| *name)
+}
+
+fn action_from_name(name: &str) -> Option<InputEvent> {
+    ACTION_NAMES
+        .iter()
+        .find(|(_, n)| *n == name)
+        .map(|(event, _)| *event)
+}
+
+fn invalid_config(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 帮助和命令面板都由默认绑定生成，每个动作都要有配置名和说明
+    #[test]
+    fn default_bindings_have_names_and_descriptions() {
+        for (state, event, keys) in DEFAULT_BINDINGS {
+            assert!(action_name(*event).is_some(), "{:?} 没有配置名", event);
+            assert!(!action_description(*state, *event).is_empty(), "{:?} 没有说明", event);
+            assert!(!keys.is_empty());
+        }
+    }
+
+    #[test]
+    fn config_replaces_keys_of_one_action() {
+        let config: KeymapConfig = toml::from_str("[detail_page]\nshow_help = [\"h\"]").unwrap();
+        let keymap = Keymap::from_config(&config).unwrap();
+        let key = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+
+        assert_eq!(keymap.lookup(AppState::DetailPage, key('h')), Some(InputEvent::ShowHelp));
+        assert_eq!(keymap.lookup(AppState::DetailPage, key('?')), None);
+        assert_eq!(keymap.lookup(AppState::MainView, key('?')), Some(InputEvent::ShowHelp));
+    }
+
+    #[test]
+    fn shift_distinguishes_letter_chords() {
+        let config: KeymapConfig = toml::from_str("[main_view]\nredo = [\"ctrl+shift+z\"]").unwrap();
+        let keymap = Keymap::from_config(&config).unwrap();
+        let key = |c, modifiers| KeyEvent::new(KeyCode::Char(c), modifiers);
+        let ctrl = KeyModifiers::CONTROL;
+        let ctrl_shift = KeyModifiers::CONTROL | KeyModifiers::SHIFT;
+
+        assert_eq!(keymap.lookup(AppState::MainView, key('z', ctrl)), Some(InputEvent::Undo));
+        // 终端可能报告小写或大写字母，带不带 Shift 标记
+        assert_eq!(keymap.lookup(AppState::MainView, key('z', ctrl_shift)), Some(InputEvent::Redo));
+        assert_eq!(keymap.lookup(AppState::MainView, key('Z', ctrl_shift)), Some(InputEvent::Redo));
+        assert_eq!(keymap.lookup(AppState::MainView, key('Z', ctrl)), Some(InputEvent::Redo));
+        assert!(KeyChord::parse("ctrl+shift+z").unwrap() != KeyChord::parse("ctrl+z").unwrap());
+
+        // 符号本身已经是按下 Shift 后的字符
+        assert_eq!(keymap.lookup(AppState::MainView, key('?', KeyModifiers::SHIFT)), Some(InputEvent::ShowHelp));
+    }
+}
+
@@ -1,26 +1,58 @@
-use std::io;
-
-use crate::input::run_app;
+use crate::input::{run_app, TerminalEvents};
 use crate::app::App;
-use ratatui::backend::CrosstermBackend;
+use crate::config::Config;
+use crate::keymap::Keymap;
+use crate::storage::Storage;
+use crate::terminal::TerminalGuard;
 
 mod app;
+mod clipboard;
+mod config;
+mod editor;
+mod events;
+mod highlight;
+mod history;
 mod input;
+mod keymap;
+mod markup;
+mod output;
+mod palette;
+mod prompt;
+mod search;
+mod storage;
+mod template;
+mod terminal;
+mod text_view;
 mod types;
+mod typing;
+#[cfg(target_os = "linux")]
+mod uinput;
 mod ui;
 mod utils;
 
 // 主函数
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // 创建终端
-    let backend = CrosstermBackend::new(std::io::stdout());
-    let mut terminal = ratatui::Terminal::new(backend)?;
-    
-    // 创建应用实例
-    let mut app = App::new();
-    
-    // 运行应用
-    run_app(&mut terminal, &mut app)?;
-    
+    // 加载数据文件
+    let storage = Storage::open_default()?;
+    let path = storage.path().display().to_string();
+    let mut app = App::with_storage(storage)
+        .map_err(|e| format!("无法读取数据文件 {}: {}", path, e))?;
+
+    // 加载配置文件
+    if let Some(config_path) = Config::default_path() {
+        let config_error = |e| format!("无法读取配置文件 {}: {}", config_path.display(), e);
+        let config = Config::load(&config_path).map_err(config_error)?;
+        app.keymap = Keymap::from_config(&config.keymap).map_err(config_error)?;
+        app.output_sink = config.output.sink;
+        app.typing_settings = config.typing;
+    }
+
+    // 运行应用，守卫离开作用域时恢复终端，之后才返回错误
+    let result = {
+        let mut guard = TerminalGuard::new()?;
+        run_app(guard.terminal(), &mut app, TerminalEvents)
+    };
+    result?;
+
     Ok(())
 }
@@ -0,0 +1,239 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::output::{Key, KeyAction, Modifiers};
+
+/// 文本标记的解析错误，位置以行号和列号（从 1 开始，按字符计）表示
+#[derive(Debug, PartialEq)]
+pub struct MarkupError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for MarkupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "第 {} 行第 {} 列: {}", self.line, self.column, self.message)
+    }
+}
+
+/// 把带标记的文本解析为按键动作序列
+///
+/// 普通字符原样输入，换行和制表符输入 Enter 和 Tab。花括号中是特殊动作：
+/// `{ENTER}`、`{TAB}` 等按键，`{CTRL+A}`、`{ALT+SHIFT+TAB}` 等组合键，
+/// `{WAIT 500}` 等待 500 毫秒，`{{` 输入一个左花括号。
+pub fn parse(text: &str) -> Result<Vec<KeyAction>, MarkupError> {
+    let mut actions = Vec::new();
+    let mut chars = text.chars().peekable();
+    let (mut line, mut column) = (1, 1);
+
+    while let Some(c) = chars.next() {
+        let start = (line, column);
+        advance(&mut line, &mut column, c);
+
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                column += 1;
+                actions.push(KeyAction::Char('{'));
+            }
+            '{' => {
+                let mut body = String::new();
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    advance(&mut line, &mut column, c);
+                    if c == '}' {
+                        closed = true;
+                        break;
+                    }
+                    body.push(c);
+                }
+
+                let error = |message: String| MarkupError {
+                    line: start.0,
+                    column: start.1,
+                    message,
+                };
+                if !closed || body.contains('\n') {
+                    return Err(error("{ 没有对应的 }，输入左花括号请写 {{".to_string()));
+                }
+                actions.push(parse_action(&body).map_err(error)?);
+            }
+            '\r' => {}
+            '\n' => actions.push(KeyAction::press(Key::Enter)),
+            '\t' => actions.push(KeyAction::press(Key::Tab)),
+            c => actions.push(KeyAction::Char(c)),
+        }
+    }
+
+    Ok(actions)
+}
+
+/// 把普通文本转义为标记文本，使其中的花括号原样输出
+pub fn escape(text: &str) -> String {
+    text.replace('{', "{{")
+}
+
+/// 按读到的字符推进行号和列号
+pub fn advance(line: &mut usize, column: &mut usize, c: char) {
+    if c == '\n' {
+        *line += 1;
+        *column = 1;
+    } else {
+        *column += 1;
+    }
+}
+
+/// 解析花括号中的内容
+fn parse_action(body: &str) -> Result<KeyAction, String> {
+    let body = body.trim();
+    if body.is_empty() {
+        return Err("{} 中缺少按键名称".to_string());
+    }
+
+    if let Some(rest) = strip_prefix_ignore_case(body, "WAIT") {
+        let ms = rest
+            .trim()
+            .parse::<u64>()
+            .map_err(|_| format!("{{{}}} 中的等待时间应为毫秒数，例如 {{WAIT 500}}", body))?;
+        return Ok(KeyAction::Wait(Duration::from_millis(ms)));
+    }
+
+    let parts: Vec<&str> = body.split('+').map(str::trim).collect();
+    let (key, mods) = match parts.split_last().expect("split 至少返回一段") {
+        // `{+}` 和 `{CTRL++}` 中最后两段为空，表示加号键
+        (&"", [mods @ .., ""]) => ("+", mods),
+        (key, mods) => (*key, mods),
+    };
+
+    let mut modifiers = Modifiers::default();
+    for m in mods {
+        let flag = match m.to_ascii_uppercase().as_str() {
+            "CTRL" | "CONTROL" => &mut modifiers.ctrl,
+            "ALT" => &mut modifiers.alt,
+            "SHIFT" => &mut modifiers.shift,
+            "WIN" | "SUPER" | "META" => &mut modifiers.win,
+            _ => return Err(format!("{{{}}} 中的修饰键 {:?} 无效，可用 CTRL、ALT、SHIFT、WIN", body, m)),
+        };
+        *flag = true;
+    }
+
+    let key = parse_key(key).ok_or_else(|| format!("{{{}}} 中的按键 {:?} 无效", body, key))?;
+    Ok(KeyAction::Key(key, modifiers))
+}
+
+/// 解析按键名称，单个字符表示该字符键
+fn parse_key(name: &str) -> Option<Key> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(Key::Char(c.to_ascii_lowercase()));
+    }
+
+    let upper = name.to_ascii_uppercase();
+    let key = match upper.as_str() {
+        "ENTER" | "RETURN" => Key::Enter,
+        "TAB" => Key::Tab,
+        "ESC" | "ESCAPE" => Key::Escape,
+        "BACKSPACE" | "BS" => Key::Backspace,
+        "DELETE" | "DEL" => Key::Delete,
+        "INSERT" | "INS" => Key::Insert,
+        "SPACE" => Key::Space,
+        "UP" => Key::Up,
+        "DOWN" => Key::Down,
+        "LEFT" => Key::Left,
+        "RIGHT" => Key::Right,
+        "HOME" => Key::Home,
+        "END" => Key::End,
+        "PGUP" | "PAGEUP" => Key::PageUp,
+        "PGDN" | "PAGEDOWN" => Key::PageDown,
+        f => match f.strip_prefix('F').and_then(|n| n.parse::<u8>().ok()) {
+            Some(n @ 1..=12) => Key::F(n),
+            _ => return None,
+        },
+    };
+    Some(key)
+}
+
+fn strip_prefix_ignore_case<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = text.get(..prefix.len())?;
+    let rest = &text[prefix.len()..];
+    (head.eq_ignore_ascii_case(prefix) && (rest.is_empty() || rest.starts_with(' '))).then_some(rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctrl(key: Key) -> KeyAction {
+        KeyAction::Key(key, Modifiers { ctrl: true, ..Modifiers::default() })
+    }
+
+    #[test]
+    fn plain_text_maps_newline_and_tab() {
+        assert_eq!(
+            parse("a\r\n\tB").unwrap(),
+            vec![
+                KeyAction::Char('a'),
+                KeyAction::press(Key::Enter),
+                KeyAction::press(Key::Tab),
+                KeyAction::Char('B'),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_keys_chords_and_waits() {
+        assert_eq!(
+            parse("{CTRL+A}x{tab}{WAIT 500}{Alt+Shift+F4}{ENTER}").unwrap(),
+            vec![
+                ctrl(Key::Char('a')),
+                KeyAction::Char('x'),
+                KeyAction::press(Key::Tab),
+                KeyAction::Wait(Duration::from_millis(500)),
+                KeyAction::Key(
+                    Key::F(4),
+                    Modifiers { alt: true, shift: true, ..Modifiers::default() }
+                ),
+                KeyAction::press(Key::Enter),
+            ]
+        );
+    }
+
+    #[test]
+    fn double_brace_is_literal() {
+        assert_eq!(
+            parse("{{x}").unwrap(),
+            vec![KeyAction::Char('{'), KeyAction::Char('x'), KeyAction::Char('}')]
+        );
+    }
+
+    #[test]
+    fn plus_key_alone_and_in_chords() {
+        assert_eq!(
+            parse("{+}{CTRL++}{ctrl + shift + +}").unwrap(),
+            vec![
+                KeyAction::press(Key::Char('+')),
+                ctrl(Key::Char('+')),
+                KeyAction::Key(Key::Char('+'), Modifiers { ctrl: true, shift: true, ..Modifiers::default() }),
+            ]
+        );
+        // 修饰键后面缺少按键
+        assert!(parse("{CTRL+}").is_err());
+        assert!(parse("{++}").is_err());
+    }
+
+    #[test]
+    fn reports_position_of_errors() {
+        let error = parse("ab\ncd{FOO}").unwrap_err();
+        assert_eq!((error.line, error.column), (2, 3));
+        assert!(error.message.contains("FOO"));
+
+        let error = parse("x{ENTER").unwrap_err();
+        assert_eq!((error.line, error.column), (1, 2));
+
+        assert!(parse("{WAIT soon}").is_err());
+        assert!(parse("{HYPER+A}").is_err());
+        assert!(parse("{}").is_err());
+        assert!(parse("{F13}").is_err());
+    }
+}
@@ -0,0 +1,417 @@
+use std::io::{self, IsTerminal, Write};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::clipboard;
+
+/// 输出到目标窗口的一个按键动作
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KeyAction {
+    /// 输入一个字符
+    Char(char),
+    /// 按下一个按键，可以同时按住修饰键
+    Key(Key, Modifiers),
+    /// 等待一段时间再继续输出
+    Wait(Duration),
+}
+
+impl KeyAction {
+    /// 不带修饰键地按下一个按键
+    pub fn press(key: Key) -> KeyAction {
+        KeyAction::Key(key, Modifiers::default())
+    }
+}
+
+/// 可以在文本标记中使用的按键
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Key {
+    /// 组合键中的字符键，例如 `{CTRL+A}` 中的 a
+    Char(char),
+    Enter,
+    Tab,
+    Escape,
+    Backspace,
+    Delete,
+    Insert,
+    Space,
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    F(u8),
+}
+
+impl Key {
+    /// 按键对应的文本，用于只能输出文本的输出目标
+    fn as_text(self) -> Option<char> {
+        match self {
+            Key::Char(c) => Some(c),
+            Key::Enter => Some('\n'),
+            Key::Tab => Some('\t'),
+            Key::Space => Some(' '),
+            _ => None,
+        }
+    }
+}
+
+/// 组合键中按住的修饰键
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    /// Windows 键 / Super 键
+    pub win: bool,
+}
+
+impl Modifiers {
+    pub fn is_empty(&self) -> bool {
+        *self == Modifiers::default()
+    }
+}
+
+/// 只能输出文本的输出目标把按键动作转换为字符，组合键和等待被忽略
+fn action_text(action: &KeyAction) -> Option<char> {
+    match action {
+        KeyAction::Char(c) => Some(*c),
+        KeyAction::Key(key, modifiers) if modifiers.is_empty() => key.as_text(),
+        _ => None,
+    }
+}
+
+/// 按键动作中的纯文本，用于复制到剪贴板
+pub fn plain_text(actions: &[KeyAction]) -> String {
+    actions.iter().filter_map(action_text).collect()
+}
+
+/// 按键动作的输出目标
+pub trait OutputSink: Send {
+    /// 输出一个字符或按键，等待动作由输出任务处理，不会交给输出目标
+    fn send(&mut self, action: &KeyAction) -> io::Result<()>;
+
+    /// 开始输出前检查全部动作都能输出，有一个不能输出时整段都不输出
+    fn check(&self, _actions: &[KeyAction]) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// 全部动作输出完成后调用
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// 是否需要在按键之间等待，只有真正模拟键盘的输出目标需要
+    fn needs_pacing(&self) -> bool {
+        true
+    }
+
+    /// 打开后至少等待多久才能输出，输出任务的倒计时不会短于这个时间
+    fn settle_delay(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    /// 用户是否按下了全局停止热键，输出任务会在每个按键前检查
+    ///
+    /// 目前只有 Windows 支持（任意窗口按 Esc）。uinput 只能写入按键，读不到其他窗口中的按键，
+    /// Linux 上需要回到本程序按取消键。
+    fn stop_requested(&self) -> bool {
+        false
+    }
+}
+
+/// 配置文件中可选的输出目标
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SinkKind {
+    /// 按平台选择：Windows 使用 keybd_event，Linux 使用 uinput
+    #[default]
+    Auto,
+    Windows,
+    Uinput,
+    /// 旧的配置文件中写作 stdout
+    #[serde(alias = "stdout")]
+    Stderr,
+    Clipboard,
+    /// 只用于测试，配置文件中不能选择
+    #[serde(skip_deserializing)]
+    #[cfg_attr(not(test), allow(dead_code))]
+    Recorder,
+}
+
+/// 打开指定的输出目标，Recorder 使用传入的记录器
+pub fn open_sink(kind: SinkKind, recorder: &RecordingSink) -> io::Result<Box<dyn OutputSink>> {
+    match kind {
+        SinkKind::Auto if cfg!(windows) => open_sink(SinkKind::Windows, recorder),
+        SinkKind::Auto if cfg!(target_os = "linux") => open_sink(SinkKind::Uinput, recorder),
+        SinkKind::Auto => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "当前平台不支持模拟键盘输出",
+        )),
+        #[cfg(windows)]
+        SinkKind::Windows => Ok(Box::new(WindowsSink)),
+        #[cfg(target_os = "linux")]
+        SinkKind::Uinput => Ok(Box::new(UinputSink {
+            keyboard: crate::uinput::VirtualKeyboard::create()?,
+        })),
+        // 界面占用着终端，标准错误没有重定向时输出的文本会混进界面
+        SinkKind::Stderr if io::stderr().is_terminal() => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "stderr 输出目标需要把标准错误重定向到文件，例如 heillo 2> output.txt",
+        )),
+        SinkKind::Stderr => Ok(Box::new(StderrSink)),
+        SinkKind::Clipboard => Ok(Box::new(ClipboardSink::default())),
+        SinkKind::Recorder => Ok(Box::new(recorder.clone())),
+        #[allow(unreachable_patterns)]
+        kind => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("当前平台不支持输出目标 {:?}", kind),
+        )),
+    }
+}
+
+/// 通过 keybd_event 向当前焦点窗口发送按键 (Windows平台)
+#[cfg(windows)]
+pub struct WindowsSink;
+
+#[cfg(windows)]
+impl OutputSink for WindowsSink {
+    fn send(&mut self, action: &KeyAction) -> io::Result<()> {
+        use winapi::um::winuser::{VK_CONTROL, VK_LWIN, VK_MENU, VK_SHIFT};
+
+        let (vk_code, modifiers) = match action {
+            KeyAction::Char(c) => {
+                let (vk, shift) = windows_char_key(*c)?;
+                (vk, Modifiers { shift, ..Modifiers::default() })
+            }
+            KeyAction::Key(key, modifiers) => {
+                let (vk, shift) = windows_key(*key)?;
+                (vk, Modifiers { shift: modifiers.shift || shift, ..*modifiers })
+            }
+            KeyAction::Wait(_) => return Ok(()),
+        };
+
+        let held: Vec<u8> = [
+            (modifiers.ctrl, VK_CONTROL),
+            (modifiers.alt, VK_MENU),
+            (modifiers.shift, VK_SHIFT),
+            (modifiers.win, VK_LWIN),
+        ]
+        .iter()
+        .filter(|(pressed, _)| *pressed)
+        .map(|(_, vk)| *vk as u8)
+        .collect();
+
+        for vk in &held {
+            windows_key_event(*vk, true); // 按下修饰键
+        }
+        windows_key_event(vk_code, true); // 按下键
+        windows_key_event(vk_code, false); // 释放键
+        for vk in held.iter().rev() {
+            windows_key_event(*vk, false); // 释放修饰键
+        }
+        Ok(())
+    }
+
+    fn check(&self, actions: &[KeyAction]) -> io::Result<()> {
+        for action in actions {
+            match action {
+                KeyAction::Char(c) => windows_char_key(*c).map(drop)?,
+                KeyAction::Key(key, _) => windows_key(*key).map(drop)?,
+                KeyAction::Wait(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// 在任意窗口按下 Esc 都会停止输出
+    fn stop_requested(&self) -> bool {
+        use winapi::um::winuser::{GetAsyncKeyState, VK_ESCAPE};
+
+        // 最高位表示按键当前处于按下状态
+        unsafe { GetAsyncKeyState(VK_ESCAPE) as u16 & 0x8000 != 0 }
+    }
+}
+
+/// 将Unicode字符转换为虚拟键码和是否需要Shift，当前键盘布局无法输入的字符返回错误
+#[cfg(windows)]
+fn windows_char_key(c: char) -> io::Result<(u8, bool)> {
+    // 超出基本多文种平面的字符无法用一个 UTF-16 单元表示
+    let vk = match u16::try_from(c as u32) {
+        Ok(unit) => unsafe { winapi::um::winuser::VkKeyScanW(unit) },
+        Err(_) => -1,
+    };
+    if vk == -1 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("当前键盘布局无法输入 {:?}", c),
+        ));
+    }
+    Ok(((vk & 0xFF) as u8, (vk >> 8) & 0x01 != 0))
+}
+
+#[cfg(windows)]
+fn windows_key(key: Key) -> io::Result<(u8, bool)> {
+    use winapi::um::winuser::*;
+
+    let vk = match key {
+        Key::Char(c) => return windows_char_key(c),
+        Key::Enter => VK_RETURN,
+        Key::Tab => VK_TAB,
+        Key::Escape => VK_ESCAPE,
+        Key::Backspace => VK_BACK,
+        Key::Delete => VK_DELETE,
+        Key::Insert => VK_INSERT,
+        Key::Space => VK_SPACE,
+        Key::Up => VK_UP,
+        Key::Down => VK_DOWN,
+        Key::Left => VK_LEFT,
+        Key::Right => VK_RIGHT,
+        Key::Home => VK_HOME,
+        Key::End => VK_END,
+        Key::PageUp => VK_PRIOR,
+        Key::PageDown => VK_NEXT,
+        Key::F(n @ 1..=24) => VK_F1 + n as i32 - 1,
+        Key::F(n) => {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("不支持按键 F{}", n)));
+        }
+    };
+    Ok((vk as u8, false))
+}
+
+#[cfg(windows)]
+fn windows_key_event(vk: u8, pressed: bool) {
+    use winapi::um::winuser::{keybd_event, KEYEVENTF_KEYUP};
+
+    let flags = if pressed { 0 } else { KEYEVENTF_KEYUP };
+    unsafe { keybd_event(vk, 0, flags, 0) };
+}
+
+/// 通过 /dev/uinput 虚拟键盘发送按键 (Linux平台)
+#[cfg(target_os = "linux")]
+pub struct UinputSink {
+    keyboard: crate::uinput::VirtualKeyboard,
+}
+
+#[cfg(target_os = "linux")]
+impl OutputSink for UinputSink {
+    fn send(&mut self, action: &KeyAction) -> io::Result<()> {
+        match action {
+            KeyAction::Char(c) => self.keyboard.type_char(*c),
+            KeyAction::Key(key, modifiers) => self.keyboard.press(*key, *modifiers),
+            KeyAction::Wait(_) => Ok(()),
+        }
+    }
+
+    fn check(&self, actions: &[KeyAction]) -> io::Result<()> {
+        crate::uinput::check(actions)
+    }
+
+    fn settle_delay(&self) -> Duration {
+        crate::uinput::SETTLE_DELAY
+    }
+}
+
+/// 把文本写到标准错误，用于调试；标准输出被界面占用
+pub struct StderrSink;
+
+impl OutputSink for StderrSink {
+    fn send(&mut self, action: &KeyAction) -> io::Result<()> {
+        match action_text(action) {
+            Some(c) => write!(io::stderr(), "{}", c),
+            None => Ok(()),
+        }
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        io::stderr().flush()
+    }
+
+    fn needs_pacing(&self) -> bool {
+        false
+    }
+}
+
+/// 收集全部文本，结束时一次性复制到剪贴板
+#[derive(Default)]
+pub struct ClipboardSink {
+    text: String,
+}
+
+impl OutputSink for ClipboardSink {
+    fn send(&mut self, action: &KeyAction) -> io::Result<()> {
+        if let Some(c) = action_text(action) {
+            self.text.push(c);
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        clipboard::copy(&self.text)
+    }
+
+    fn needs_pacing(&self) -> bool {
+        false
+    }
+}
+
+/// 在内存中记录收到的按键动作，克隆出的记录器共享同一份记录
+#[derive(Clone, Default)]
+pub struct RecordingSink {
+    actions: Arc<Mutex<Vec<KeyAction>>>,
+}
+
+impl RecordingSink {
+    /// 取出目前记录的全部动作
+    #[cfg(test)]
+    pub fn take(&self) -> Vec<KeyAction> {
+        std::mem::take(&mut *self.actions.lock().unwrap())
+    }
+}
+
+impl OutputSink for RecordingSink {
+    fn send(&mut self, action: &KeyAction) -> io::Result<()> {
+        self.actions.lock().unwrap().push(*action);
+        Ok(())
+    }
+
+    fn needs_pacing(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_sinks_keep_only_plain_text() {
+        let actions = [
+            KeyAction::Char('a'),
+            KeyAction::press(Key::Enter),
+            KeyAction::press(Key::Tab),
+            KeyAction::Key(Key::Char('c'), Modifiers { ctrl: true, ..Modifiers::default() }),
+            KeyAction::Wait(Duration::from_millis(5)),
+            KeyAction::press(Key::Escape),
+            KeyAction::press(Key::Space),
+        ];
+        assert_eq!(plain_text(&actions), "a\n\t ");
+    }
+
+    #[test]
+    fn recorder_clones_share_actions() {
+        let recorder = RecordingSink::default();
+        let mut sink = open_sink(SinkKind::Recorder, &recorder).unwrap();
+        let actions = [KeyAction::Char('h'), KeyAction::press(Key::Enter)];
+        for action in &actions {
+            sink.send(action).unwrap();
+        }
+
+        assert_eq!(recorder.take(), actions);
+        assert!(recorder.take().is_empty());
+    }
+}
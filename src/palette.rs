@@ -0,0 +1,143 @@
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use ratatui::widgets::ListState;
+
+use crate::editor::Editor;
+use crate::keymap::{action_description, action_name, Keymap, KeyChord};
+use crate::types::{AppState, InputEvent};
+
+/// 命令面板中的一个动作
+pub struct PaletteEntry {
+    pub event: InputEvent,
+    pub description: &'static str,
+    pub keys: Vec<KeyChord>,
+    /// description 中匹配的字符序号，用于高亮
+    pub indices: Vec<usize>,
+}
+
+/// 命令面板：列出打开面板前所在视图的全部动作，可以模糊过滤后执行
+pub struct Palette {
+    pub editor: Editor,
+    pub entries: Vec<PaletteEntry>,
+    pub list: ListState,
+    /// 打开面板前的视图，执行的动作在该视图中生效
+    pub return_state: AppState,
+    /// 该视图的全部动作：说明、配置名和按键
+    actions: Vec<(InputEvent, &'static str, &'static str, Vec<KeyChord>)>,
+}
+
+impl Palette {
+    pub fn new(keymap: &Keymap, return_state: AppState) -> Palette {
+        let actions = keymap
+            .bindings(return_state)
+            .iter()
+            .filter(|b| b.event != InputEvent::Help)
+            .map(|b| {
+                (
+                    b.event,
+                    action_description(return_state, b.event),
+                    action_name(b.event).unwrap_or(""),
+                    b.keys.clone(),
+                )
+            })
+            .collect();
+        let mut palette = Palette {
+            editor: Editor::default(),
+            entries: Vec::new(),
+            list: ListState::default(),
+            return_state,
+            actions,
+        };
+        palette.update();
+        palette
+    }
+
+    /// 按输入的关键字过滤动作，说明和配置名都可以匹配，关键字为空时按绑定顺序列出全部动作
+    pub fn update(&mut self) {
+        let query = self.editor.text();
+        let query = query.trim();
+        let matcher = SkimMatcherV2::default();
+
+        let mut scored: Vec<(i64, PaletteEntry)> = self
+            .actions
+            .iter()
+            .filter_map(|(event, description, name, keys)| {
+                let (score, indices) = if query.is_empty() {
+                    (0, Vec::new())
+                } else {
+                    let by_description = matcher.fuzzy_indices(description, query);
+                    let by_name = matcher.fuzzy_match(name, query).map(|score| (score, Vec::new()));
+                    match (by_description, by_name) {
+                        (Some(d), Some(n)) if n.0 > d.0 => n,
+                        (Some(d), _) => d,
+                        (None, n) => n?,
+                    }
+                };
+                let entry = PaletteEntry {
+                    event: *event,
+                    description,
+                    keys: keys.clone(),
+                    indices,
+                };
+                Some((score, entry))
+            })
+            .collect();
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+
+        self.entries = scored.into_iter().map(|(_, entry)| entry).collect();
+        self.list.select(if self.entries.is_empty() { None } else { Some(0) });
+    }
+
+    pub fn selected_event(&self) -> Option<InputEvent> {
+        self.list.selected().and_then(|i| self.entries.get(i)).map(|e| e.event)
+    }
+
+    pub fn next(&mut self) {
+        if let Some(i) = self.list.selected() {
+            self.list.select(Some((i + 1) % self.entries.len()));
+        }
+    }
+
+    pub fn previous(&mut self) {
+        if let Some(i) = self.list.selected() {
+            let len = self.entries.len();
+            self.list.select(Some((i + len - 1) % len));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn events(palette: &Palette) -> Vec<InputEvent> {
+        palette.entries.iter().map(|e| e.event).collect()
+    }
+
+    #[test]
+    fn lists_view_actions_except_help() {
+        let keymap = Keymap::default();
+        let palette = Palette::new(&keymap, AppState::DetailPage);
+
+        assert_eq!(palette.entries.len(), keymap.bindings(AppState::DetailPage).len() - 1);
+        assert!(!events(&palette).contains(&InputEvent::Help));
+        assert_eq!(palette.selected_event(), Some(InputEvent::Quit));
+    }
+
+    #[test]
+    fn filters_by_description_or_action_name() {
+        let mut palette = Palette::new(&Keymap::default(), AppState::DetailPage);
+        palette.editor.insert_char('复');
+        palette.editor.insert_char('制');
+        palette.update();
+        assert_eq!(events(&palette), vec![InputEvent::CopyText]);
+        assert_eq!(palette.entries[0].indices, vec![0, 1]);
+
+        let mut palette = Palette::new(&Keymap::default(), AppState::DetailPage);
+        for c in "simout".chars() {
+            palette.editor.insert_char(c);
+        }
+        palette.update();
+        assert_eq!(palette.selected_event(), Some(InputEvent::SimulateOutput));
+    }
+}
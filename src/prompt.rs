@@ -0,0 +1,99 @@
+use std::collections::BTreeMap;
+
+use crate::editor::Editor;
+use crate::template::Variable;
+use crate::types::AppState;
+
+/// 填写完变量后的操作
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PromptAction {
+    /// 模拟键盘输出
+    Type,
+    /// 复制到剪贴板
+    Copy,
+}
+
+/// 输出前填写模板变量的表单，当前选中的变量在编辑器中编辑
+pub struct PromptForm {
+    pub variables: Vec<Variable>,
+    values: Vec<String>,
+    selected: usize,
+    pub editor: Editor,
+    /// 表单所属的详情项：订阅序号和详情项序号
+    pub subscription: usize,
+    pub detail: usize,
+    /// 关闭表单后返回的视图
+    pub return_state: AppState,
+    pub action: PromptAction,
+}
+
+impl PromptForm {
+    /// 创建表单，优先填入上次使用的值，其次是模板中的默认值
+    pub fn new(
+        variables: Vec<Variable>,
+        remembered: &BTreeMap<String, String>,
+        (subscription, detail): (usize, usize),
+        return_state: AppState,
+        action: PromptAction,
+    ) -> PromptForm {
+        let values: Vec<String> = variables
+            .iter()
+            .map(|v| {
+                remembered
+                    .get(&v.name)
+                    .or(v.default.as_ref())
+                    .cloned()
+                    .unwrap_or_default()
+            })
+            .collect();
+        let editor = Editor::new(values.first().map(String::as_str).unwrap_or(""));
+
+        PromptForm {
+            variables,
+            values,
+            selected: 0,
+            editor,
+            subscription,
+            detail,
+            return_state,
+            action,
+        }
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    /// 第 index 个变量当前的值，选中的变量返回编辑器中的内容
+    pub fn value(&self, index: usize) -> String {
+        if index == self.selected {
+            self.editor.text()
+        } else {
+            self.values[index].clone()
+        }
+    }
+
+    pub fn next_field(&mut self) {
+        self.select((self.selected + 1) % self.variables.len());
+    }
+
+    pub fn previous_field(&mut self) {
+        let len = self.variables.len();
+        self.select((self.selected + len - 1) % len);
+    }
+
+    /// 全部变量的值
+    pub fn values(&self) -> BTreeMap<String, String> {
+        self.variables
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (v.name.clone(), self.value(i)))
+            .collect()
+    }
+
+    fn select(&mut self, index: usize) {
+        self.values[self.selected] = self.editor.text();
+        self.selected = index;
+        self.editor = Editor::new(&self.values[index]);
+    }
+}
@@ -0,0 +1,180 @@
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use ratatui::widgets::ListState;
+
+use crate::editor::Editor;
+use crate::types::{AppState, Subscription};
+
+/// 搜索结果匹配到的字段
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SearchField {
+    Subscription,
+    Title,
+    Describe,
+    Text,
+}
+
+/// 一条搜索结果
+#[derive(Debug, PartialEq)]
+pub struct SearchHit {
+    pub subscription: usize,
+    /// 匹配到订阅名时为 None
+    pub detail: Option<usize>,
+    pub field: SearchField,
+    /// 匹配到的一行文本，多行的字段只保留匹配最好的一行
+    pub line: String,
+    /// line 中匹配的字符序号，用于高亮
+    pub indices: Vec<usize>,
+    pub score: i64,
+}
+
+/// 模糊搜索订阅名以及详情项的标题、描述和文本，按匹配程度从高到低排列
+///
+/// 每个订阅和详情项只保留匹配最好的字段，得分相同时按列表中的顺序排列。
+pub fn search(items: &[Subscription], query: &str) -> Vec<SearchHit> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    let matcher = SkimMatcherV2::default();
+    let mut hits = Vec::new();
+    for (i, subscription) in items.iter().enumerate() {
+        hits.extend(best_match(&matcher, query, i, None, &[(SearchField::Subscription, &subscription.name)]));
+
+        for (j, item) in subscription.details.iter().enumerate() {
+            let fields = [
+                (SearchField::Title, &item.title),
+                (SearchField::Describe, &item.describe),
+                (SearchField::Text, &item.text),
+            ];
+            hits.extend(best_match(&matcher, query, i, Some(j), &fields));
+        }
+    }
+
+    // 稳定排序，得分相同的结果保持原来的顺序
+    hits.sort_by_key(|hit| std::cmp::Reverse(hit.score));
+    hits
+}
+
+/// 在几个字段的每一行中查找得分最高的匹配
+fn best_match(
+    matcher: &SkimMatcherV2,
+    query: &str,
+    subscription: usize,
+    detail: Option<usize>,
+    fields: &[(SearchField, &String)],
+) -> Option<SearchHit> {
+    let mut best: Option<SearchHit> = None;
+    for (field, text) in fields {
+        for line in text.lines() {
+            let Some((score, indices)) = matcher.fuzzy_indices(line, query) else {
+                continue;
+            };
+            if best.as_ref().is_none_or(|b| score > b.score) {
+                best = Some(SearchHit {
+                    subscription,
+                    detail,
+                    field: *field,
+                    line: line.to_string(),
+                    indices,
+                    score,
+                });
+            }
+        }
+    }
+    best
+}
+
+/// 搜索界面的状态：输入框中的关键字和当前的搜索结果
+pub struct SearchState {
+    pub editor: Editor,
+    pub hits: Vec<SearchHit>,
+    pub list: ListState,
+    /// 取消搜索后返回的视图
+    pub return_state: AppState,
+}
+
+impl SearchState {
+    pub fn new(return_state: AppState) -> SearchState {
+        SearchState {
+            editor: Editor::default(),
+            hits: Vec::new(),
+            list: ListState::default(),
+            return_state,
+        }
+    }
+
+    /// 关键字修改后重新搜索，并选中第一条结果
+    pub fn update(&mut self, items: &[Subscription]) {
+        self.hits = search(items, &self.editor.text());
+        self.list.select(if self.hits.is_empty() { None } else { Some(0) });
+    }
+
+    pub fn selected_hit(&self) -> Option<&SearchHit> {
+        self.list.selected().and_then(|i| self.hits.get(i))
+    }
+
+    pub fn next(&mut self) {
+        if let Some(i) = self.list.selected() {
+            self.list.select(Some((i + 1) % self.hits.len()));
+        }
+    }
+
+    pub fn previous(&mut self) {
+        if let Some(i) = self.list.selected() {
+            let len = self.hits.len();
+            self.list.select(Some((i + len - 1) % len));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::DetailItem;
+
+    fn item(title: &str, describe: &str, text: &str) -> DetailItem {
+        DetailItem::new(title, describe, text)
+    }
+
+    fn items() -> Vec<Subscription> {
+        let mut servers = Subscription::new("Servers".to_string());
+        servers.details.push(item("Login", "ssh to prod", "ssh root@prod\nuptime"));
+        servers.details.push(item("Deploy", "", "git pull\ncargo build --release"));
+        let mut notes = Subscription::new("Notes".to_string());
+        notes.details.push(item("Greeting", "hello", "Hello, world"));
+        vec![servers, notes]
+    }
+
+    #[test]
+    fn matches_every_field_and_keeps_best_line() {
+        let items = items();
+
+        let hits = search(&items, "cbr");
+        assert_eq!(hits.len(), 1);
+        assert_eq!((hits[0].subscription, hits[0].detail), (0, Some(1)));
+        assert_eq!(hits[0].field, SearchField::Text);
+        assert_eq!(hits[0].line, "cargo build --release");
+        assert_eq!(hits[0].indices, vec![0, 6, 14]);
+
+        let hits = search(&items, "notes");
+        assert_eq!(hits[0].field, SearchField::Subscription);
+        assert_eq!(hits[0].detail, None);
+    }
+
+    #[test]
+    fn ranks_better_matches_first() {
+        let hits = search(&items(), "prod");
+        assert_eq!(hits.len(), 1);
+
+        let hits = search(&items(), "lo");
+        assert!(hits.len() >= 2);
+        assert!(hits.windows(2).all(|w| w[0].score >= w[1].score));
+    }
+
+    #[test]
+    fn empty_query_has_no_results() {
+        assert!(search(&items(), "  ").is_empty());
+    }
+}
@@ -0,0 +1,267 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::template;
+use crate::types::{DetailItem, Subscription};
+
+/// 当前数据文件的结构版本
+pub const SCHEMA_VERSION: u32 = 3;
+
+/// 数据文件名
+const DATA_FILE_NAME: &str = "data.json";
+
+/// 写入磁盘的数据结构
+#[derive(Serialize, Deserialize)]
+pub struct StoredData {
+    pub version: u32,
+    pub subscriptions: Vec<Subscription>,
+}
+
+/// 版本 1 的数据格式：所有订阅共用同一个详情项列表
+#[derive(Deserialize)]
+struct StoredDataV1 {
+    items: Vec<String>,
+    detail_items: Vec<DetailItem>,
+}
+
+/// 负责在磁盘上读写应用数据
+pub struct Storage {
+    path: PathBuf,
+}
+
+impl Storage {
+    pub fn new(path: PathBuf) -> Storage {
+        Storage { path }
+    }
+
+    /// 使用用户数据目录下的默认数据文件
+    pub fn open_default() -> io::Result<Storage> {
+        let dir = dirs::data_dir().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "无法确定用户数据目录")
+        })?;
+        Ok(Storage::new(dir.join("heillo").join(DATA_FILE_NAME)))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 读取数据文件，文件不存在时返回 None
+    pub fn load(&self) -> io::Result<Option<StoredData>> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        let value: serde_json::Value = serde_json::from_str(&content).map_err(invalid_data)?;
+        migrate(value).map(Some)
+    }
+
+    /// 写入数据文件，先写临时文件再重命名，避免写到一半时损坏原文件
+    pub fn save(&self, data: &StoredData) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let content = serde_json::to_string_pretty(data).map_err(invalid_data)?;
+        let tmp_path = self.path.with_extension("json.tmp");
+        fs::write(&tmp_path, content)?;
+        fs::rename(&tmp_path, &self.path)
+    }
+}
+
+/// 按版本号把旧格式的数据逐步升级到当前格式
+fn migrate(value: serde_json::Value) -> io::Result<StoredData> {
+    let version = value
+        .get("version")
+        .and_then(|v| v.as_u64())
+        .ok_or_else(|| invalid_data("数据文件缺少 version 字段"))?;
+
+    let mut data = match version {
+        1 => {
+            // 旧版本中每个订阅看到的都是同一份详情项，升级时给每个订阅复制一份
+            let old: StoredDataV1 = serde_json::from_value(value).map_err(invalid_data)?;
+            let subscriptions = old
+                .items
+                .into_iter()
+                .map(|name| Subscription {
+                    name,
+                    details: old.detail_items.clone(),
+                })
+                .collect();
+            StoredData {
+                version: 2,
+                subscriptions,
+            }
+        }
+        2..=3 => serde_json::from_value(value).map_err(invalid_data)?,
+        _ => {
+            return Err(invalid_data(format!(
+                "不支持的数据文件版本 {}（当前版本 {}）",
+                version, SCHEMA_VERSION
+            )))
+        }
+    };
+
+    if data.version < 3 {
+        // 版本 3 开始文本中的 `${…}` 表示模板变量、花括号表示按键标记，旧文本转义后仍按原样输出
+        for item in data.subscriptions.iter_mut().flat_map(|s| s.details.iter_mut()) {
+            item.text = template::escape(&item.text);
+        }
+    }
+    data.version = SCHEMA_VERSION;
+    Ok(data)
+}
+
+fn invalid_data<E>(error: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::{Key, KeyAction};
+
+    /// 每个测试使用临时目录中自己的数据文件
+    fn temp_storage(name: &str) -> Storage {
+        let dir = std::env::temp_dir().join(format!("heillo-test-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        Storage::new(dir.join(DATA_FILE_NAME))
+    }
+
+    fn item(title: &str, text: &str) -> DetailItem {
+        DetailItem::new(title, "", text)
+    }
+
+    fn titles(data: &StoredData) -> Vec<(String, Vec<String>)> {
+        data.subscriptions
+            .iter()
+            .map(|s| (s.name.clone(), s.details.iter().map(|d| d.title.clone()).collect()))
+            .collect()
+    }
+
+    #[test]
+    fn missing_file_loads_as_none() {
+        let storage = temp_storage("missing");
+        assert!(storage.load().unwrap().is_none());
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let storage = temp_storage("round-trip");
+        let mut servers = Subscription::new("Servers".to_string());
+        servers.details.push(item("Login", "ssh root@prod"));
+        let data = StoredData {
+            version: SCHEMA_VERSION,
+            subscriptions: vec![servers, Subscription::new("Empty".to_string())],
+        };
+
+        storage.save(&data).unwrap();
+        let loaded = storage.load().unwrap().unwrap();
+        assert_eq!(loaded.version, SCHEMA_VERSION);
+        assert_eq!(titles(&loaded), titles(&data));
+        assert_eq!(loaded.subscriptions[0].details[0].text, "ssh root@prod");
+    }
+
+    #[test]
+    fn migrates_version_1_shared_details() {
+        let storage = temp_storage("v1");
+        fs::create_dir_all(storage.path().parent().unwrap()).unwrap();
+        let v1 = r#"{
+            "version": 1,
+            "items": ["Servers", "Notes"],
+            "detail_items": [
+                {"title": "Login", "describe": "ssh to prod", "text": "ssh root@prod"}
+            ]
+        }"#;
+        fs::write(storage.path(), v1).unwrap();
+
+        let loaded = storage.load().unwrap().unwrap();
+        assert_eq!(loaded.version, SCHEMA_VERSION);
+        assert_eq!(
+            titles(&loaded),
+            vec![
+                ("Servers".to_string(), vec!["Login".to_string()]),
+                ("Notes".to_string(), vec!["Login".to_string()]),
+            ]
+        );
+        let login = &loaded.subscriptions[1].details[0];
+        assert_eq!((login.describe.as_str(), login.text.as_str()), ("ssh to prod", "ssh root@prod"));
+    }
+
+    /// 文本展开模板后输出的字符，换行按 Enter 记为 '\n'；文本中不能有需要填写的变量
+    fn typed(text: &str) -> String {
+        let segments = template::parse(text).unwrap();
+        assert!(template::variables(&segments).is_empty());
+        let context = &mut template::FixedContext::default();
+        let expanded = template::expand(&segments, &Default::default(), 1, context).unwrap();
+        crate::markup::parse(&expanded)
+            .unwrap()
+            .into_iter()
+            .map(|action| match action {
+                KeyAction::Char(c) => c,
+                KeyAction::Key(Key::Enter, _) => '\n',
+                _ => panic!("不是普通字符"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn version_2_text_still_types_verbatim() {
+        let storage = temp_storage("v2-braces");
+        fs::create_dir_all(storage.path().parent().unwrap()).unwrap();
+        let text = "fn main() {\n    let v = {a,b};\n}\n{ y }\necho ${HOME} ${VAR:-x}\nprint:\n    echo $${HOME} $$$${x}";
+        let v2 = serde_json::json!({
+            "version": 2,
+            "subscriptions": [{"name": "Code", "details": [{"title": "Main", "describe": "", "text": text}]}]
+        });
+        fs::write(storage.path(), v2.to_string()).unwrap();
+
+        let loaded = storage.load().unwrap().unwrap();
+        assert_eq!(loaded.version, SCHEMA_VERSION);
+        assert_eq!(typed(&loaded.subscriptions[0].details[0].text), text);
+
+        // 升级后保存的文件再读取时不会重复转义
+        storage.save(&loaded).unwrap();
+        assert_eq!(typed(&storage.load().unwrap().unwrap().subscriptions[0].details[0].text), text);
+    }
+
+    #[test]
+    fn rejects_newer_version() {
+        let storage = temp_storage("newer");
+        fs::create_dir_all(storage.path().parent().unwrap()).unwrap();
+        let newer = format!(r#"{{"version": {}, "subscriptions": []}}"#, SCHEMA_VERSION + 1);
+        fs::write(storage.path(), newer).unwrap();
+
+        let error = storage.load().err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("不支持的数据文件版本"));
+    }
+
+    #[test]
+    fn interrupted_save_keeps_old_file() {
+        let storage = temp_storage("interrupted");
+        let data = StoredData {
+            version: SCHEMA_VERSION,
+            subscriptions: vec![Subscription::new("Servers".to_string())],
+        };
+        storage.save(&data).unwrap();
+
+        // 写完临时文件、重命名之前中断：临时文件残留且内容不完整
+        fs::write(storage.path().with_extension("json.tmp"), r#"{"version": 2, "subscr"#).unwrap();
+        let loaded = storage.load().unwrap().unwrap();
+        assert_eq!(titles(&loaded), titles(&data));
+
+        // 下一次保存覆盖残留的临时文件
+        storage.save(&StoredData { version: SCHEMA_VERSION, subscriptions: Vec::new() }).unwrap();
+        assert!(storage.load().unwrap().unwrap().subscriptions.is_empty());
+        assert!(!storage.path().with_extension("json.tmp").exists());
+    }
+}
@@ -0,0 +1,563 @@
+use std::collections::BTreeMap;
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::io;
+use std::time::SystemTime;
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local};
+
+use crate::clipboard;
+use crate::markup::{self, advance, MarkupError};
+use crate::types::Subscription;
+
+/// 内置占位符的名称，不会作为需要填写的变量
+const BUILTINS: &[&str] = &["date", "env", "uuid", "counter", "clipboard", INCLUDE];
+
+/// 引用其他详情项文本的占位符：`${include:订阅名/标题}`
+const INCLUDE: &str = "include";
+
+/// `${date}` 没有指定格式时使用的日期格式
+const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// 模板文本的一段：普通文本或 `${name}` / `${name:default}` 占位符
+#[derive(Debug, PartialEq)]
+pub enum Segment {
+    Text(String),
+    Placeholder { name: String, arg: Option<String> },
+}
+
+/// 需要用户填写的模板变量
+#[derive(Clone, Debug, PartialEq)]
+pub struct Variable {
+    pub name: String,
+    pub default: Option<String>,
+}
+
+/// 解析模板文本，`{` 前面的一串 `$` 多写一个表示字面文本：`$${` 是 `${`，`$$${` 是 `$${`
+///
+/// 普通文本原样保留，展开后的文本再交给 [`markup::parse`] 解析按键标记。
+pub fn parse(text: &str) -> Result<Vec<Segment>, MarkupError> {
+    let mut segments = Vec::new();
+    let mut literal = String::new();
+    let mut chars = text.chars().peekable();
+    let (mut line, mut column) = (1, 1);
+
+    while let Some(c) = chars.next() {
+        let start = (line, column);
+        advance(&mut line, &mut column, c);
+
+        if c != '$' {
+            literal.push(c);
+            continue;
+        }
+
+        // 连续的 `$` 作为一组处理，后面没有 `{` 时原样输出
+        let mut dollars = 1;
+        while chars.peek() == Some(&'$') {
+            chars.next();
+            advance(&mut line, &mut column, '$');
+            dollars += 1;
+        }
+        if chars.peek() != Some(&'{') {
+            literal.extend(std::iter::repeat_n('$', dollars));
+            continue;
+        }
+        chars.next();
+        advance(&mut line, &mut column, '{');
+
+        // 去掉一个 `$` 后输出字面文本，其中的花括号转义后不会被当作按键标记
+        if dollars > 1 {
+            literal.extend(std::iter::repeat_n('$', dollars - 1));
+            literal.push_str("{{");
+            continue;
+        }
+
+        let mut body = String::new();
+        let mut closed = false;
+        for c in chars.by_ref() {
+            advance(&mut line, &mut column, c);
+            if c == '}' {
+                closed = true;
+                break;
+            }
+            body.push(c);
+        }
+
+        let error = |message: String| MarkupError {
+            line: start.0,
+            column: start.1,
+            message,
+        };
+        if !closed || body.contains('\n') {
+            return Err(error("${ 没有对应的 }，输入字面的 ${ 请写 $${".to_string()));
+        }
+
+        let (name, arg) = match body.split_once(':') {
+            Some((name, arg)) => (name.trim(), Some(arg.to_string())),
+            None => (body.trim(), None),
+        };
+        if name.is_empty() || !name.chars().all(is_name_char) {
+            return Err(error(format!(
+                "变量名 {:?} 无效，只能包含字母、数字、下划线和连字符",
+                name
+            )));
+        }
+
+        if !literal.is_empty() {
+            segments.push(Segment::Text(std::mem::take(&mut literal)));
+        }
+        segments.push(Segment::Placeholder {
+            name: name.to_string(),
+            arg,
+        });
+    }
+
+    if !literal.is_empty() {
+        segments.push(Segment::Text(literal));
+    }
+    Ok(segments)
+}
+
+/// 把普通文本转义为模板文本，使其中的 `${` 和花括号都原样输出
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            // 紧跟在一串 `$` 后的花括号：多写一个 `$`，解析时去掉后输出原来的整串
+            '{' if escaped.ends_with('$') => escaped.push_str("${"),
+            '{' => escaped.push_str("{{"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// 内置占位符使用的外部数据来源，测试时可以注入固定的时钟和随机数
+pub trait Context {
+    fn now(&self) -> DateTime<Local>;
+    fn random(&mut self) -> u128;
+    fn env(&self, name: &str) -> Option<String>;
+    fn clipboard(&self) -> io::Result<String>;
+}
+
+/// 使用系统时钟、环境变量和剪贴板的数据来源
+pub struct SystemContext;
+
+impl Context for SystemContext {
+    fn now(&self) -> DateTime<Local> {
+        Local::now()
+    }
+
+    /// 每次使用随机种子不同的哈希器生成随机数，不需要密码学强度
+    fn random(&mut self) -> u128 {
+        let high = RandomState::new().hash_one(SystemTime::now());
+        let low = RandomState::new().hash_one(high);
+        (high as u128) << 64 | low as u128
+    }
+
+    fn env(&self, name: &str) -> Option<String> {
+        std::env::var(name).ok()
+    }
+
+    fn clipboard(&self) -> io::Result<String> {
+        clipboard::paste()
+    }
+}
+
+/// 解析指定详情项的文本，并把其中的 `${include:…}` 替换为被引用详情项的文本
+///
+/// 被引用的文本与原文本一样处理模板变量和按键标记，可以继续引用其他详情项。
+pub fn parse_item(items: &[Subscription], location: (usize, usize)) -> Result<Vec<Segment>, String> {
+    parse_included(items, &mut vec![location])
+}
+
+/// 按 `订阅名/标题` 查找详情项，订阅名和标题中都可以包含斜杠
+pub fn find_item(items: &[Subscription], reference: &str) -> Option<(usize, usize)> {
+    items.iter().enumerate().find_map(|(i, subscription)| {
+        let title = reference.strip_prefix(subscription.name.as_str())?.strip_prefix('/')?;
+        let j = subscription.details.iter().position(|d| d.title == title)?;
+        Some((i, j))
+    })
+}
+
+/// 解析引用链最后一个详情项的文本，stack 中是正在展开的引用链，用于发现循环引用
+fn parse_included(items: &[Subscription], stack: &mut Vec<(usize, usize)>) -> Result<Vec<Segment>, String> {
+    let location = *stack.last().expect("引用链不能为空");
+    let segments = parse(&items[location.0].details[location.1].text).map_err(|e| {
+        if stack.len() == 1 {
+            format!("文本标记错误，{}", e)
+        } else {
+            format!("被引用的 {} 文本标记错误，{}", item_path(items, location), e)
+        }
+    })?;
+
+    let mut resolved = Vec::new();
+    for segment in segments {
+        let Segment::Placeholder { name, arg } = &segment else {
+            resolved.push(segment);
+            continue;
+        };
+        if name != INCLUDE {
+            resolved.push(segment);
+            continue;
+        }
+
+        let reference = arg.as_deref().unwrap_or("").trim();
+        let target = find_item(items, reference)
+            .ok_or_else(|| format!("找不到引用的详情项 {:?}", reference))?;
+        if let Some(start) = stack.iter().position(|l| *l == target) {
+            let cycle: Vec<String> = stack[start..]
+                .iter()
+                .chain([&target])
+                .map(|l| item_path(items, *l))
+                .collect();
+            return Err(format!("循环引用: {}", cycle.join(" -> ")));
+        }
+
+        stack.push(target);
+        resolved.extend(parse_included(items, stack)?);
+        stack.pop();
+    }
+    Ok(resolved)
+}
+
+/// 详情项在引用中的写法
+fn item_path(items: &[Subscription], (i, j): (usize, usize)) -> String {
+    format!("{}/{}", items[i].name, items[i].details[j].title)
+}
+
+pub fn is_builtin(name: &str) -> bool {
+    BUILTINS.contains(&name)
+}
+
+/// 模板中是否使用了 `${counter}`
+pub fn uses_counter(segments: &[Segment]) -> bool {
+    segments
+        .iter()
+        .any(|s| matches!(s, Segment::Placeholder { name, .. } if name == "counter"))
+}
+
+/// 模板中需要用户填写的变量，按第一次出现的顺序排列，同名变量只出现一次
+pub fn variables(segments: &[Segment]) -> Vec<Variable> {
+    let mut variables: Vec<Variable> = Vec::new();
+    for segment in segments {
+        let Segment::Placeholder { name, arg } = segment else {
+            continue;
+        };
+        if is_builtin(name) {
+            continue;
+        }
+        match variables.iter_mut().find(|v| v.name == *name) {
+            // 第一次出现时没有默认值的变量使用后面出现的默认值
+            Some(variable) => {
+                if variable.default.is_none() {
+                    variable.default = arg.clone();
+                }
+            }
+            None => variables.push(Variable {
+                name: name.clone(),
+                default: arg.clone(),
+            }),
+        }
+    }
+    variables
+}
+
+/// 展开模板：变量使用填写的值或默认值，内置占位符从 context 中取值，counter 为本次的序号
+///
+/// 替换进来的值中的花括号会被转义，不会被当作按键标记。
+pub fn expand(
+    segments: &[Segment],
+    values: &BTreeMap<String, String>,
+    counter: u64,
+    context: &mut dyn Context,
+) -> Result<String, String> {
+    substitute(segments, |name, arg| {
+        if is_builtin(name) {
+            builtin(name, arg, counter, context)
+        } else {
+            Ok(values.get(name).or(arg.as_ref()).cloned().unwrap_or_default())
+        }
+    })
+}
+
+/// 检查文本中的模板和按键标记，占位符按空文本处理
+pub fn validate(text: &str) -> Result<(), MarkupError> {
+    let segments = parse(text)?;
+    let text = substitute(&segments, |_, _| Ok(String::new())).unwrap_or_default();
+    markup::parse(&text).map(|_| ())
+}
+
+fn substitute(
+    segments: &[Segment],
+    mut value: impl FnMut(&str, &Option<String>) -> Result<String, String>,
+) -> Result<String, String> {
+    let mut text = String::new();
+    for segment in segments {
+        match segment {
+            Segment::Text(literal) => text.push_str(literal),
+            Segment::Placeholder { name, arg } => text.push_str(&markup::escape(&value(name, arg)?)),
+        }
+    }
+    Ok(text)
+}
+
+/// 计算内置占位符的值
+fn builtin(name: &str, arg: &Option<String>, counter: u64, context: &mut dyn Context) -> Result<String, String> {
+    match name {
+        "date" => {
+            let format = arg.as_deref().unwrap_or(DEFAULT_DATE_FORMAT);
+            // 格式无效时 chrono 会在格式化时 panic，这里提前检查
+            if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+                return Err(format!("${{date:{}}} 的日期格式无效", format));
+            }
+            Ok(context.now().format(format).to_string())
+        }
+        "env" => {
+            let variable = arg.as_deref().unwrap_or("").trim();
+            if variable.is_empty() {
+                return Err("${env:名称} 中缺少环境变量名称".to_string());
+            }
+            context
+                .env(variable)
+                .ok_or_else(|| format!("环境变量 {} 不存在", variable))
+        }
+        "uuid" => Ok(format_uuid(context.random())),
+        "counter" => Ok(counter.to_string()),
+        "clipboard" => context
+            .clipboard()
+            .map_err(|e| format!("无法读取剪贴板: {}", e)),
+        // 引用在 parse_item 中已经展开，只有单独解析的文本中会剩下
+        INCLUDE => Err("${include:…} 只能在详情项的文本中使用".to_string()),
+        _ => unreachable!("未知的内置占位符 {}", name),
+    }
+}
+
+/// 把随机数格式化为第 4 版 UUID
+fn format_uuid(random: u128) -> String {
+    // 第 7 个字节的高 4 位是版本号，第 9 个字节的高 2 位是变体
+    let value = random & !(0xF << 76) | 0x4 << 76;
+    let value = value & !(0x3 << 62) | 0x2 << 62;
+    format!(
+        "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+        value >> 96,
+        (value >> 80) & 0xFFFF,
+        (value >> 64) & 0xFFFF,
+        (value >> 48) & 0xFFFF,
+        value & 0xFFFF_FFFF_FFFF
+    )
+}
+
+/// 固定的数据来源，用于测试内置占位符
+#[cfg(test)]
+pub struct FixedContext {
+    pub now: DateTime<Local>,
+    pub random: u128,
+    pub env: BTreeMap<String, String>,
+    pub clipboard: String,
+}
+
+#[cfg(test)]
+impl Default for FixedContext {
+    fn default() -> FixedContext {
+        use chrono::TimeZone;
+
+        FixedContext {
+            now: Local.with_ymd_and_hms(2024, 3, 5, 14, 7, 9).unwrap(),
+            random: 0x0123_4567_89ab_cdef_0123_4567_89ab_cdef,
+            env: BTreeMap::from([("USER".to_string(), "alice".to_string())]),
+            clipboard: "copied".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl Context for FixedContext {
+    fn now(&self) -> DateTime<Local> {
+        self.now
+    }
+
+    /// 每次调用后加一，使同一模板中的多个 UUID 不同
+    fn random(&mut self) -> u128 {
+        let value = self.random;
+        self.random = self.random.wrapping_add(1);
+        value
+    }
+
+    fn env(&self, name: &str) -> Option<String> {
+        self.env.get(name).cloned()
+    }
+
+    fn clipboard(&self) -> io::Result<String> {
+        Ok(self.clipboard.clone())
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variable(name: &str, default: Option<&str>) -> Variable {
+        Variable {
+            name: name.to_string(),
+            default: default.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn collects_unique_variables_in_order() {
+        let segments = parse("ssh ${user:root}@${host} # ${host:localhost} ${user}").unwrap();
+        assert_eq!(
+            variables(&segments),
+            vec![variable("user", Some("root")), variable("host", Some("localhost"))]
+        );
+    }
+
+    #[test]
+    fn expands_values_and_defaults() {
+        let segments = parse("ssh ${user:root}@${host}").unwrap();
+        let values = BTreeMap::from([("host".to_string(), "db{1}".to_string())]);
+        assert_eq!(
+            expand(&segments, &values, 1, &mut FixedContext::default()).unwrap(),
+            "ssh root@db{{1}"
+        );
+    }
+
+    #[test]
+    fn dollar_escapes_and_plain_dollars() {
+        let segments = parse("$${x} costs $5 or $$").unwrap();
+        assert!(variables(&segments).is_empty());
+        let text = expand(&segments, &BTreeMap::new(), 1, &mut FixedContext::default()).unwrap();
+        assert_eq!(text, "${{x} costs $5 or $$");
+        assert_eq!(
+            markup::parse(&text).unwrap().len(),
+            "${x} costs $5 or $$".chars().count()
+        );
+    }
+
+    #[test]
+    fn dollar_runs_before_brace_drop_one_dollar() {
+        let segments = parse("$$${x} $$$${y} $${z}").unwrap();
+        assert!(variables(&segments).is_empty());
+        let text = expand(&segments, &BTreeMap::new(), 1, &mut FixedContext::default()).unwrap();
+        assert_eq!(text, "$${{x} $$${{y} ${{z}");
+
+        // 只有紧跟在 `{` 前的一个 `$` 才是占位符
+        assert_eq!(
+            parse("$$ ${x}").unwrap(),
+            [
+                Segment::Text("$$ ".to_string()),
+                Segment::Placeholder { name: "x".to_string(), arg: None }
+            ]
+        );
+    }
+
+    #[test]
+    fn escaped_text_types_verbatim() {
+        let texts = [
+            "echo ${HOME}",
+            "echo $${HOME}",
+            "$$$${x}",
+            "${VAR:-x} {a,b} ${{",
+            "fn main() {\n}",
+            "$5 $$ {{ $",
+        ];
+        for text in texts {
+            let segments = parse(&escape(text)).unwrap();
+            assert!(variables(&segments).is_empty(), "{}", text);
+            let expanded = expand(&segments, &BTreeMap::new(), 1, &mut FixedContext::default()).unwrap();
+            let typed = crate::output::plain_text(&markup::parse(&expanded).unwrap());
+            assert_eq!(typed, text);
+        }
+    }
+
+    #[test]
+    fn reports_malformed_placeholders() {
+        let error = parse("a\n  ${host").unwrap_err();
+        assert_eq!((error.line, error.column), (2, 3));
+        assert!(parse("${}").is_err());
+        assert!(parse("${two words}").is_err());
+    }
+
+    #[test]
+    fn builtins_are_not_prompted() {
+        let segments = parse("${date} ${env:USER} ${uuid} ${counter} ${clipboard} ${name}").unwrap();
+        assert_eq!(variables(&segments), vec![variable("name", None)]);
+        assert!(uses_counter(&segments));
+    }
+
+    #[test]
+    fn expands_builtins_from_context() {
+        let segments = parse("${date} ${date:%H:%M:%S} ${env:USER} #${counter} ${clipboard}").unwrap();
+        let text = expand(&segments, &BTreeMap::new(), 7, &mut FixedContext::default()).unwrap();
+        assert_eq!(text, "2024-03-05 14:07:09 alice #7 copied");
+    }
+
+    #[test]
+    fn uuid_is_version_4() {
+        let segments = parse("${uuid} ${uuid}").unwrap();
+        let text = expand(&segments, &BTreeMap::new(), 1, &mut FixedContext::default()).unwrap();
+        assert_eq!(
+            text,
+            "01234567-89ab-4def-8123-456789abcdef 01234567-89ab-4def-8123-456789abcdf0"
+        );
+    }
+
+    #[test]
+    fn reports_builtin_errors() {
+        let mut context = FixedContext::default();
+        let mut expand_text = |text: &str| expand(&parse(text).unwrap(), &BTreeMap::new(), 1, &mut context);
+
+        assert!(expand_text("${env:MISSING}").unwrap_err().contains("MISSING"));
+        assert!(expand_text("${env}").is_err());
+        assert!(expand_text("${date:%Q}").is_err());
+    }
+
+    fn items(texts: &[(&str, &str, &str)]) -> Vec<Subscription> {
+        let mut items: Vec<Subscription> = Vec::new();
+        for (name, title, text) in texts {
+            if items.last().is_none_or(|s| s.name != *name) {
+                items.push(Subscription::new(name.to_string()));
+            }
+            items.last_mut().unwrap().details.push(crate::types::DetailItem::new(*title, "", *text));
+        }
+        items
+    }
+
+    #[test]
+    fn includes_expand_recursively_with_their_variables() {
+        let items = items(&[
+            ("a/b", "header", "# ${author:me}\n${include:common/sig}"),
+            ("common", "sig", "-- ${date}"),
+            ("common", "body", "${include:a/b/header}\nbody ${name}"),
+        ]);
+        let segments = parse_item(&items, (1, 1)).unwrap();
+        assert_eq!(
+            variables(&segments),
+            vec![variable("author", Some("me")), variable("name", None)]
+        );
+
+        let values = BTreeMap::from([("name".to_string(), "x".to_string())]);
+        let text = expand(&segments, &values, 1, &mut FixedContext::default()).unwrap();
+        assert_eq!(text, "# me\n-- 2024-03-05\nbody x");
+    }
+
+    #[test]
+    fn reports_missing_and_cyclic_includes() {
+        let items = items(&[
+            ("s", "a", "${include:s/b}"),
+            ("s", "b", "x ${include:s/a}"),
+            ("s", "c", "${include:s/none}"),
+            ("s", "d", "${include:s/d}"),
+        ]);
+
+        assert_eq!(parse_item(&items, (0, 0)).unwrap_err(), "循环引用: s/a -> s/b -> s/a");
+        assert!(parse_item(&items, (0, 2)).unwrap_err().contains("s/none"));
+        assert_eq!(parse_item(&items, (0, 3)).unwrap_err(), "循环引用: s/d -> s/d");
+    }
+}
@@ -0,0 +1,66 @@
+use std::io::{self, Stdout};
+use std::panic;
+
+use crossterm::cursor::Show;
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::Terminal;
+use ratatui::backend::CrosstermBackend;
+
+pub type Tui = Terminal<CrosstermBackend<Stdout>>;
+
+/// 终端守卫：创建时进入原始模式和备用屏幕，销毁时恢复终端
+pub struct TerminalGuard {
+    terminal: Tui,
+}
+
+impl TerminalGuard {
+    pub fn new() -> io::Result<TerminalGuard> {
+        install_panic_hook();
+
+        enable_raw_mode()?;
+        let setup = execute!(io::stdout(), EnterAlternateScreen)
+            .and_then(|_| Terminal::new(CrosstermBackend::new(io::stdout())));
+        match setup {
+            Ok(terminal) => Ok(TerminalGuard { terminal }),
+            Err(e) => {
+                // 初始化到一半失败时也要把终端恢复原样
+                let _ = restore();
+                Err(e)
+            }
+        }
+    }
+
+    pub fn terminal(&mut self) -> &mut Tui {
+        &mut self.terminal
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = restore();
+    }
+}
+
+/// 退出原始模式、离开备用屏幕并显示光标
+///
+/// 某一步失败时仍会执行其余步骤，尽量把终端恢复原样，返回第一个错误。
+pub fn restore() -> io::Result<()> {
+    let steps = [
+        disable_raw_mode(),
+        execute!(io::stdout(), LeaveAlternateScreen),
+        execute!(io::stdout(), Show),
+    ];
+    steps.into_iter().collect()
+}
+
+/// 发生 panic 时先恢复终端，再交给默认的处理函数打印错误信息
+fn install_panic_hook() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let _ = restore();
+        default_hook(info);
+    }));
+}
@@ -0,0 +1,266 @@
+use std::ops::Range;
+
+use unicode_width::UnicodeWidthChar;
+
+use crate::editor::display_width;
+
+/// 水平滚动一次移动的列数
+const HSCROLL_STEP: usize = 4;
+
+/// 文本区块中的一个显示行：原文的第几行，以及该行中从 start 到 end 的字符
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct VisualRow {
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// 文本在区块中的排列：不换行时每个原文行占一个显示行，自动换行时按宽度折成多行
+pub struct TextLayout {
+    pub rows: Vec<VisualRow>,
+    /// 最长一行的显示宽度，用于限制水平滚动
+    pub max_width: usize,
+}
+
+impl TextLayout {
+    pub fn new(lines: &[&str], width: usize, wrap: bool) -> TextLayout {
+        let width = width.max(1);
+        let mut rows = Vec::new();
+        let mut max_width = 0;
+
+        for (i, line) in lines.iter().enumerate() {
+            max_width = max_width.max(display_width(line.chars()));
+            let len = line.chars().count();
+            if !wrap {
+                rows.push(VisualRow { line: i, start: 0, end: len });
+                continue;
+            }
+
+            // 宽字符放不下时整个移到下一行，每行至少放一个字符
+            let mut start = 0;
+            let mut row_width = 0;
+            for (j, c) in line.chars().enumerate() {
+                let w = c.width().unwrap_or(0);
+                if j > start && row_width + w > width {
+                    rows.push(VisualRow { line: i, start, end: j });
+                    start = j;
+                    row_width = 0;
+                }
+                row_width += w;
+            }
+            rows.push(VisualRow { line: i, start, end: len });
+        }
+
+        TextLayout { rows, max_width }
+    }
+
+    /// 光标（原文的行和字符序号）所在的显示行和该行中的显示列
+    pub fn cursor_position(&self, lines: &[&str], (line, col): (usize, usize)) -> (usize, usize) {
+        let index = self
+            .rows
+            .iter()
+            .rposition(|row| row.line == line && row.start <= col)
+            .unwrap_or(0);
+        let row = self.rows[index];
+        let x = display_width(lines[line].chars().skip(row.start).take(col.saturating_sub(row.start)));
+        (index, x)
+    }
+}
+
+/// 详情页面文本区块的滚动位置和显示选项，编辑模式和浏览时共用
+pub struct TextView {
+    /// 向下滚动的显示行数
+    pub scroll: usize,
+    /// 向右滚动的列数，自动换行时不使用
+    pub hscroll: usize,
+    pub wrap: bool,
+    pub line_numbers: bool,
+    /// 一页可见的行数，绘制时更新，用于翻页
+    pub page: usize,
+    /// 上次绘制的详情项，换到其他详情项时回到开头
+    item: Option<(usize, usize)>,
+}
+
+impl Default for TextView {
+    fn default() -> TextView {
+        TextView {
+            scroll: 0,
+            hscroll: 0,
+            wrap: false,
+            line_numbers: true,
+            page: 1,
+            item: None,
+        }
+    }
+}
+
+impl TextView {
+    pub fn scroll_up(&mut self, rows: usize) {
+        self.scroll = self.scroll.saturating_sub(rows);
+    }
+
+    pub fn scroll_down(&mut self, rows: usize) {
+        self.scroll = self.scroll.saturating_add(rows);
+    }
+
+    pub fn scroll_left(&mut self) {
+        self.hscroll = self.hscroll.saturating_sub(HSCROLL_STEP);
+    }
+
+    pub fn scroll_right(&mut self) {
+        if !self.wrap {
+            self.hscroll = self.hscroll.saturating_add(HSCROLL_STEP);
+        }
+    }
+
+    pub fn toggle_wrap(&mut self) {
+        self.wrap = !self.wrap;
+        self.hscroll = 0;
+    }
+
+    /// 记录正在显示的详情项，与上次不同时回到开头
+    pub fn show_item(&mut self, item: (usize, usize)) {
+        if self.item != Some(item) {
+            self.item = Some(item);
+            self.scroll = 0;
+            self.hscroll = 0;
+        }
+    }
+
+    /// 按可见区域的大小调整滚动位置：限制在内容范围内，有光标时让光标保持可见
+    pub fn fit(&mut self, layout: &TextLayout, width: usize, height: usize, cursor: Option<(usize, usize)>) {
+        let height = height.max(1);
+        self.page = height;
+        self.scroll = self.scroll.min(layout.rows.len().saturating_sub(height));
+        self.hscroll = if self.wrap {
+            0
+        } else {
+            // 编辑时光标可以停在最长一行的末尾之后
+            let content_width = layout.max_width + usize::from(cursor.is_some());
+            self.hscroll.min(content_width.saturating_sub(width))
+        };
+
+        let Some((row, x)) = cursor else {
+            return;
+        };
+        if row < self.scroll {
+            self.scroll = row;
+        } else if row >= self.scroll + height {
+            self.scroll = row + 1 - height;
+        }
+        if !self.wrap {
+            if x < self.hscroll {
+                self.hscroll = x;
+            } else if x >= self.hscroll + width {
+                self.hscroll = x + 1 - width;
+            }
+        }
+    }
+}
+
+/// 一行中从第 start 列开始、宽度不超过 width 的字符范围，以及两端被切开的宽字符留下的空白列数
+pub fn column_range(line: &str, start: usize, width: usize) -> (usize, Range<usize>, usize) {
+    let (mut lead, mut trail) = (0, 0);
+    let (mut begin, mut end) = (None, 0);
+    let mut x = 0;
+    for (i, c) in line.chars().enumerate() {
+        let w = c.width().unwrap_or(0);
+        let (from, to) = (x, x + w);
+        x = to;
+        if to <= start {
+            continue;
+        }
+        if to > start + width {
+            if from < start + width {
+                trail = start + width - from.max(start);
+            }
+            break;
+        }
+        if from < start {
+            lead = to - start;
+        } else {
+            begin.get_or_insert(i);
+            end = i + 1;
+        }
+    }
+    (lead, begin.unwrap_or(end)..end, trail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 取出一行中从第 start 列开始、宽度不超过 width 的部分，被切开的宽字符用空格代替
+    fn slice_columns(line: &str, start: usize, width: usize) -> String {
+        let (lead, range, trail) = column_range(line, start, width);
+        let mut text: String = std::iter::repeat_n(' ', lead).collect();
+        text.extend(line.chars().skip(range.start).take(range.len()));
+        text.extend(std::iter::repeat_n(' ', trail));
+        text
+    }
+
+    fn rows(layout: &TextLayout) -> Vec<(usize, usize, usize)> {
+        layout.rows.iter().map(|r| (r.line, r.start, r.end)).collect()
+    }
+
+    #[test]
+    fn wraps_lines_by_display_width() {
+        let lines = ["abcdefg", "", "中文字符"];
+        let layout = TextLayout::new(&lines, 3, true);
+        assert_eq!(
+            rows(&layout),
+            vec![(0, 0, 3), (0, 3, 6), (0, 6, 7), (1, 0, 0), (2, 0, 1), (2, 1, 2), (2, 2, 3), (2, 3, 4)]
+        );
+        assert_eq!(layout.max_width, 8);
+
+        let layout = TextLayout::new(&lines, 3, false);
+        assert_eq!(rows(&layout), vec![(0, 0, 7), (1, 0, 0), (2, 0, 4)]);
+    }
+
+    #[test]
+    fn finds_cursor_in_wrapped_rows() {
+        let lines = ["abcdefg", "xy"];
+        let layout = TextLayout::new(&lines, 3, true);
+        assert_eq!(layout.cursor_position(&lines, (0, 0)), (0, 0));
+        assert_eq!(layout.cursor_position(&lines, (0, 4)), (1, 1));
+        assert_eq!(layout.cursor_position(&lines, (0, 7)), (2, 1));
+        assert_eq!(layout.cursor_position(&lines, (1, 2)), (3, 2));
+    }
+
+    #[test]
+    fn scroll_follows_cursor_and_stays_in_range() {
+        let lines: Vec<String> = (0..20).map(|i| format!("line {}", i)).collect();
+        let lines: Vec<&str> = lines.iter().map(|s| s.as_str()).collect();
+        let layout = TextLayout::new(&lines, 4, false);
+        let mut view = TextView::default();
+
+        view.scroll_down(100);
+        view.fit(&layout, 4, 5, None);
+        assert_eq!((view.scroll, view.page), (15, 5));
+
+        view.fit(&layout, 4, 5, Some((3, 6)));
+        assert_eq!((view.scroll, view.hscroll), (3, 3));
+
+        view.fit(&layout, 4, 5, Some((9, 0)));
+        assert_eq!((view.scroll, view.hscroll), (5, 0));
+    }
+
+    #[test]
+    fn new_item_scrolls_back_to_top() {
+        let mut view = TextView::default();
+        view.show_item((0, 0));
+        view.scroll_down(3);
+        view.show_item((0, 0));
+        assert_eq!(view.scroll, 3);
+        view.show_item((0, 1));
+        assert_eq!(view.scroll, 0);
+    }
+
+    #[test]
+    fn slices_columns_around_wide_chars() {
+        assert_eq!(slice_columns("abcdef", 2, 3), "cde");
+        assert_eq!(slice_columns("中文字", 1, 4), " 文 ");
+        assert_eq!(slice_columns("ab", 5, 3), "");
+        assert_eq!(column_range("中文字", 1, 4), (1, 1..2, 1));
+    }
+}
@@ -1,23 +1,73 @@
-use ratatui::widgets::ListState;
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::typing::TypingOverrides;
 
 /// 表示详情项目的结构体
-#[derive(Clone)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct DetailItem {
     pub title: String,
     pub describe: String,
     pub text: String,
+    /// 文本的语言，用于语法高亮；没有设置时按内容自动识别
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// 模拟键盘输出时使用的节奏设置
+    #[serde(default, skip_serializing_if = "TypingOverrides::is_empty")]
+    pub typing: TypingOverrides,
+    /// 上次输出时填写的模板变量值
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub variables: BTreeMap<String, String>,
+    /// 文本中 `${counter}` 上次输出的序号
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub counter: u64,
+}
+
+impl DetailItem {
+    /// 只有标题、描述和文本的详情项，其余字段使用默认值
+    pub fn new(title: impl Into<String>, describe: impl Into<String>, text: impl Into<String>) -> DetailItem {
+        DetailItem {
+            title: title.into(),
+            describe: describe.into(),
+            text: text.into(),
+            ..DetailItem::default()
+        }
+    }
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+/// 表示一个订阅，拥有自己的详情项列表
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Subscription {
+    pub name: String,
+    pub details: Vec<DetailItem>,
+}
+
+impl Subscription {
+    pub fn new(name: String) -> Subscription {
+        Subscription {
+            name,
+            details: Vec::new(),
+        }
+    }
 }
 
 /// 详情页面中的可选项枚举
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum DetailSelection {
     Title,
+    Language,
     Describe,
     Text,
+    Typing,
 }
 
 /// 键盘输入事件类型
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum InputEvent {
     Quit,
     Help,
@@ -31,19 +81,46 @@ pub enum InputEvent {
     Down,
     Left,
     Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    WordLeft,
+    WordRight,
+    Delete,
     EditMode,
     SaveSelection,
+    DiscardEdit,
+    SimulateOutput,
+    CancelOutput,
+    CopyText,
+    NextDetail,
+    TogglePreview,
+    Search,
+    ShowHelp,
+    ScrollUp,
+    ScrollDown,
+    ScrollLeft,
+    ScrollRight,
+    ToggleWrap,
+    ToggleLineNumbers,
     CharacterInput(char),
     Backspace,
+    Undo,
+    Redo,
     OtherKey,
 }
 
 /// 应用程序状态枚举
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum AppState {
     MainView,      // 主视图
     DetailView,    // 详细视图
     DetailPage,    // 详情页面
     EditMode,      // 编辑模式
     SaveMode,      // 保存选择模式
+    PromptMode,    // 填写模板变量
+    SearchMode,    // 搜索
+    PaletteMode,   // 命令面板
+    HelpMode,      // 帮助
 }
\ No newline at end of file
@@ -0,0 +1,415 @@
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::events::{AppEvent, EventSender};
+use crate::output::{Key, KeyAction, OutputSink};
+
+/// 等待期间检查取消请求的间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// 模拟键盘输出的节奏，时间单位为毫秒
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TypingSettings {
+    /// 开始输出前的等待时间，留给用户切换到目标窗口
+    pub start_delay_ms: u64,
+    /// 按键之间的等待时间
+    pub key_delay_ms: u64,
+    /// 每次按键额外增加 0 到该值之间的随机等待
+    pub jitter_ms: u64,
+    /// 输入换行后额外等待的时间
+    pub newline_pause_ms: u64,
+}
+
+impl Default for TypingSettings {
+    fn default() -> TypingSettings {
+        TypingSettings {
+            start_delay_ms: 2000,
+            key_delay_ms: 10,
+            jitter_ms: 0,
+            newline_pause_ms: 0,
+        }
+    }
+}
+
+/// 详情项自己的输出节奏，没有设置的项使用全局设置
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TypingOverrides {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_delay_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_delay_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jitter_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub newline_pause_ms: Option<u64>,
+}
+
+impl TypingOverrides {
+    pub fn is_empty(&self) -> bool {
+        *self == TypingOverrides::default()
+    }
+
+    /// 用详情项的设置覆盖全局设置
+    pub fn resolve(&self, global: TypingSettings) -> TypingSettings {
+        TypingSettings {
+            start_delay_ms: self.start_delay_ms.unwrap_or(global.start_delay_ms),
+            key_delay_ms: self.key_delay_ms.unwrap_or(global.key_delay_ms),
+            jitter_ms: self.jitter_ms.unwrap_or(global.jitter_ms),
+            newline_pause_ms: self.newline_pause_ms.unwrap_or(global.newline_pause_ms),
+        }
+    }
+
+    /// 以 TOML 形式列出全部设置，沿用全局设置的项写成注释，去掉注释即可单独设置
+    pub fn to_text(&self, global: TypingSettings) -> String {
+        let fields = [
+            ("start_delay_ms", self.start_delay_ms, global.start_delay_ms),
+            ("key_delay_ms", self.key_delay_ms, global.key_delay_ms),
+            ("jitter_ms", self.jitter_ms, global.jitter_ms),
+            ("newline_pause_ms", self.newline_pause_ms, global.newline_pause_ms),
+        ];
+        fields
+            .iter()
+            .map(|(name, value, default)| match value {
+                Some(value) => format!("{} = {}", name, value),
+                None => format!("# {} = {}", name, default),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// 解析详情页面中编辑的设置文本
+    pub fn parse(text: &str) -> Result<TypingOverrides, String> {
+        toml::from_str(text).map_err(|e| format!("输出设置格式错误: {}", e.message()))
+    }
+}
+
+/// 后台输出线程通过事件队列发回的进度消息
+pub enum TypingUpdate {
+    /// 距离开始输出的剩余时间
+    Countdown(Duration),
+    /// 已经输出的按键动作数量
+    Progress(usize),
+    Finished(TypingOutcome),
+}
+
+/// 输出任务的结果
+pub enum TypingOutcome {
+    Completed,
+    Cancelled,
+    Failed(io::Error),
+}
+
+/// 输出任务当前所处的阶段
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TypingPhase {
+    Countdown(Duration),
+    Typing { done: usize, total: usize },
+}
+
+/// 在后台线程中运行的模拟键盘输出任务
+pub struct TypingJob {
+    phase: TypingPhase,
+    total: usize,
+    cancel: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl TypingJob {
+    /// 启动任务：按设置等待一段时间后依次把动作交给输出目标，进度消息发送到事件队列
+    pub fn start(
+        mut sink: Box<dyn OutputSink>,
+        actions: Vec<KeyAction>,
+        settings: TypingSettings,
+        events: EventSender,
+    ) -> TypingJob {
+        let total = actions.len();
+        let cancel = Arc::new(AtomicBool::new(false));
+
+        let thread_cancel = Arc::clone(&cancel);
+        let handle = thread::spawn(move || {
+            let notify = |update| {
+                events.send(AppEvent::Typing(update));
+            };
+            let outcome = run(sink.as_mut(), &actions, settings, &thread_cancel, &notify);
+            notify(TypingUpdate::Finished(outcome));
+        });
+
+        TypingJob {
+            phase: TypingPhase::Countdown(Duration::from_millis(settings.start_delay_ms)),
+            total,
+            cancel,
+            handle: Some(handle),
+        }
+    }
+
+    pub fn phase(&self) -> TypingPhase {
+        self.phase
+    }
+
+    /// 请求停止任务，后台线程会在下一个按键前停下
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// 处理一条进度消息，任务结束时返回结果
+    pub fn update(&mut self, update: TypingUpdate) -> Option<TypingOutcome> {
+        match update {
+            TypingUpdate::Countdown(remaining) => self.phase = TypingPhase::Countdown(remaining),
+            TypingUpdate::Progress(done) => self.phase = TypingPhase::Typing { done, total: self.total },
+            TypingUpdate::Finished(outcome) => {
+                self.join();
+                return Some(outcome);
+            }
+        }
+        None
+    }
+
+    fn join(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for TypingJob {
+    /// 退出程序时停止仍在运行的任务，避免继续向其他窗口输入
+    fn drop(&mut self) {
+        self.cancel();
+        self.join();
+    }
+}
+
+/// 后台线程的主体：先倒计时，再逐个输出按键
+fn run(
+    sink: &mut dyn OutputSink,
+    actions: &[KeyAction],
+    settings: TypingSettings,
+    cancel: &AtomicBool,
+    notify: &dyn Fn(TypingUpdate),
+) -> TypingOutcome {
+    let stopped = |sink: &dyn OutputSink| cancel.load(Ordering::Relaxed) || sink.stop_requested();
+
+    // 倒计时同时用来等待输出目标准备好，不占用界面线程
+    let start_delay = Duration::from_millis(settings.start_delay_ms).max(sink.settle_delay());
+    let countdown = |remaining| notify(TypingUpdate::Countdown(remaining));
+    if !wait(start_delay, &|| stopped(sink), &countdown) {
+        return TypingOutcome::Cancelled;
+    }
+
+    let pacing = sink.needs_pacing();
+    let mut jitter = Jitter::seeded();
+    for (i, action) in actions.iter().enumerate() {
+        if stopped(sink) {
+            return TypingOutcome::Cancelled;
+        }
+        let pause = match action {
+            // 等待动作只对模拟键盘的输出目标有意义
+            KeyAction::Wait(duration) => *duration,
+            action => {
+                if let Err(e) = sink.send(action) {
+                    return TypingOutcome::Failed(e);
+                }
+                let mut pause = settings.key_delay_ms.saturating_add(jitter.below(settings.jitter_ms));
+                if *action == KeyAction::press(Key::Enter) {
+                    pause = pause.saturating_add(settings.newline_pause_ms);
+                }
+                Duration::from_millis(pause)
+            }
+        };
+        if pacing && !wait(pause, &|| stopped(sink), &|_| {}) {
+            return TypingOutcome::Cancelled;
+        }
+        notify(TypingUpdate::Progress(i + 1));
+    }
+
+    match sink.finish() {
+        Ok(()) => TypingOutcome::Completed,
+        Err(e) => TypingOutcome::Failed(e),
+    }
+}
+
+/// 分段等待 duration，每段之前检查是否需要停止并报告剩余时间，停止时返回 false
+///
+/// 等待时间来自配置，可能大到无法加到 Instant 上，所以按已经过的时间计算剩余时间。
+fn wait(duration: Duration, stopped: &dyn Fn() -> bool, tick: &dyn Fn(Duration)) -> bool {
+    let started = Instant::now();
+    loop {
+        if stopped() {
+            return false;
+        }
+        let remaining = duration.saturating_sub(started.elapsed());
+        if remaining.is_zero() {
+            return true;
+        }
+        tick(remaining);
+        thread::sleep(remaining.min(POLL_INTERVAL));
+    }
+}
+
+/// 生成随机等待时间的简单伪随机数 (xorshift)，不需要密码学强度
+struct Jitter(u64);
+
+impl Jitter {
+    fn seeded() -> Jitter {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+        // 种子不能为 0
+        Jitter(nanos | 1)
+    }
+
+    /// 返回 0 到 max（含）之间的随机数
+    fn below(&mut self, max: u64) -> u64 {
+        if max == 0 {
+            return 0;
+        }
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        match max.checked_add(1) {
+            Some(bound) => self.0 % bound,
+            None => self.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    /// 打开后需要等待一段时间才能输出的输出目标
+    #[derive(Default)]
+    struct SettlingSink {
+        sent: Vec<KeyAction>,
+    }
+
+    impl OutputSink for SettlingSink {
+        fn send(&mut self, action: &KeyAction) -> io::Result<()> {
+            self.sent.push(*action);
+            Ok(())
+        }
+
+        fn settle_delay(&self) -> Duration {
+            Duration::from_millis(120)
+        }
+    }
+
+    #[test]
+    fn overrides_text_round_trips() {
+        let overrides = TypingOverrides {
+            key_delay_ms: Some(50),
+            ..TypingOverrides::default()
+        };
+        let text = overrides.to_text(TypingSettings::default());
+
+        assert_eq!(
+            text,
+            "# start_delay_ms = 2000\nkey_delay_ms = 50\n# jitter_ms = 0\n# newline_pause_ms = 0"
+        );
+        assert_eq!(TypingOverrides::parse(&text), Ok(overrides));
+    }
+
+    #[test]
+    fn overrides_replace_only_set_fields() {
+        let overrides = TypingOverrides {
+            start_delay_ms: Some(0),
+            newline_pause_ms: Some(300),
+            ..TypingOverrides::default()
+        };
+
+        assert_eq!(
+            overrides.resolve(TypingSettings::default()),
+            TypingSettings {
+                start_delay_ms: 0,
+                key_delay_ms: 10,
+                jitter_ms: 0,
+                newline_pause_ms: 300,
+            }
+        );
+    }
+
+    #[test]
+    fn parse_rejects_unknown_settings() {
+        assert!(TypingOverrides::parse("speed = 3").is_err());
+        assert!(TypingOverrides::parse("key_delay_ms = -1").is_err());
+    }
+
+    #[test]
+    fn jitter_stays_within_bound() {
+        let mut jitter = Jitter::seeded();
+        assert_eq!(jitter.below(0), 0);
+        assert!((0..1000).all(|_| jitter.below(5) <= 5));
+        jitter.below(u64::MAX);
+    }
+
+    #[test]
+    fn huge_settings_do_not_overflow() {
+        let settings = TypingSettings {
+            start_delay_ms: u64::MAX,
+            key_delay_ms: u64::MAX,
+            jitter_ms: u64::MAX,
+            newline_pause_ms: u64::MAX,
+        };
+        let outcome = run(&mut SettlingSink::default(), &[KeyAction::Char('a')], settings, &AtomicBool::new(true), &|_| {});
+        assert!(matches!(outcome, TypingOutcome::Cancelled));
+    }
+
+    #[test]
+    fn countdown_waits_for_sink_to_settle() {
+        let mut sink = SettlingSink::default();
+        let settings = TypingSettings {
+            start_delay_ms: 0,
+            key_delay_ms: 0,
+            ..TypingSettings::default()
+        };
+        let countdowns = Cell::new(0);
+        let notify = |update| {
+            if let TypingUpdate::Countdown(_) = update {
+                countdowns.set(countdowns.get() + 1);
+            }
+        };
+
+        let started = Instant::now();
+        let outcome = run(&mut sink, &[KeyAction::Char('a')], settings, &AtomicBool::new(false), &notify);
+        assert!(matches!(outcome, TypingOutcome::Completed));
+        assert!(started.elapsed() >= sink.settle_delay());
+        assert!(countdowns.get() > 0);
+        assert_eq!(sink.sent, [KeyAction::Char('a')]);
+    }
+
+    /// 第一个按键后取消的输出目标
+    struct CancelAfterSend<'a>(&'a AtomicBool);
+
+    impl OutputSink for CancelAfterSend<'_> {
+        fn send(&mut self, _action: &KeyAction) -> io::Result<()> {
+            self.0.store(true, Ordering::Relaxed);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn long_key_pause_can_be_cancelled() {
+        let settings = TypingSettings {
+            start_delay_ms: 0,
+            key_delay_ms: u64::MAX,
+            jitter_ms: u64::MAX,
+            newline_pause_ms: u64::MAX,
+        };
+        let cancel = AtomicBool::new(false);
+        let actions = [KeyAction::press(Key::Enter), KeyAction::Char('a')];
+
+        let started = Instant::now();
+        let outcome = run(&mut CancelAfterSend(&cancel), &actions, settings, &cancel, &|_| {});
+        assert!(matches!(outcome, TypingOutcome::Cancelled));
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+}
@@ -1,120 +1,196 @@
 use ratatui::{
-    layout::{Constraint, Direction, Layout},
+    layout::{Alignment, Constraint, Direction, Layout, Margin, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, Paragraph},
+    widgets::{
+        Block, Borders, Clear, Gauge, List, ListItem, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState,
+        Wrap,
+    },
     Frame,
 };
 
-use crate::types::{DetailItem, DetailSelection};
+use crate::editor::display_width;
+use crate::input::accepts_text;
+use crate::keymap::{action_description, view_name, view_title, KeyChord, VIEWS};
+use crate::search::{SearchField, SearchHit};
+use crate::highlight::{same_text, slice_styled, StyledLine};
+use crate::text_view::{column_range, TextLayout, TextView};
+use crate::types::{AppState, DetailSelection, InputEvent, Subscription};
+use crate::typing::TypingPhase;
 use crate::app::App;
 
+/// 终端小于该大小时不绘制界面，只提示窗口太小
+const MIN_WIDTH: u16 = 40;
+const MIN_HEIGHT: u16 = 12;
+
+/// 详情页面中语言区块的宽度，位于标题区块右侧
+const LANGUAGE_WIDTH: u16 = 24;
+
+/// 终端低于该高度时说明栏收起为一行提示，把空间留给内容
+const COMPACT_HEIGHT: u16 = 20;
+
+/// 完整的说明栏最多占用终端高度的几分之一，超过时同样收起
+const HELP_HEIGHT_DIVISOR: u16 = 3;
+
 // UI渲染函数
 pub fn draw(f: &mut Frame, app: &mut App) {
+    let size = f.size();
+    if size.width < MIN_WIDTH || size.height < MIN_HEIGHT {
+        draw_too_small(f);
+        return;
+    }
+
+    // 说明栏按内容的行数占用高度，终端太矮或者内容太多时收起为一行
+    let full_height = footer_text(app, false, size.width).len() as u16 + 2;
+    let compact = size.height < COMPACT_HEIGHT || full_height > size.height / HELP_HEIGHT_DIVISOR;
+    let help_height = if compact { 1 } else { full_height };
+    let gauge_height = if app.typing.is_some() { 1 } else { 0 };
+
     // 创建主布局
     let chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Percentage(80), Constraint::Percentage(20)])
-        .split(f.size());
+        .constraints([Constraint::Min(0), Constraint::Length(help_height + gauge_height)])
+        .split(size);
+
+    // 打开命令面板时，面板下方仍然显示原来的视图
+    let view = app.palette.as_ref().map_or(app.app_state, |p| p.return_state);
 
     // 如果在详情页面中
-    if app.in_detail_page && !app.detail_items.is_empty() {
+    let editing = matches!(view, AppState::EditMode | AppState::SaveMode);
+
+    if view == AppState::PromptMode {
+        draw_prompt(f, app, chunks[0]);
+    } else if view == AppState::SearchMode {
+        draw_search(f, app, chunks[0]);
+    } else if editing || view == AppState::DetailPage {
+        // 文本区块的滚动状态在绘制时更新，先从 app 中取出，绘制后放回
+        let mut text_view = std::mem::take(&mut app.text_view);
+        text_view.show_item((app.current_subscription, app.current_detail_index));
+        let mut text_cursor = None;
+
+        // 文本中的标记或引用有错误时在标题中提示，检查结果在 app 中缓存
+        let text_error = app.text_error();
+
         // 显示详细内容
-        if app.current_detail_index < app.detail_items.len() {
-            let current_item = &app.detail_items[app.current_detail_index];
+        if let Some(current_item) = app.current_detail() {
             
-            // 创建垂直布局用于显示详细信息
+            // 创建垂直布局用于显示详细信息，标题、描述和输出设置按内容占用高度，其余留给文本
+            let describe_height = (current_item.describe.lines().count() as u16).clamp(1, 3) + 2;
+            // 输出设置只在选中时展开
+            let typing_height = if app.current_detail_selection == DetailSelection::Typing { 6 } else { 3 };
+            let (margin, describe_height, typing_height) = if size.height < COMPACT_HEIGHT {
+                (0, 3, 3)
+            } else {
+                (1, describe_height, typing_height)
+            };
             let detail_chunks = Layout::default()
                 .direction(Direction::Vertical)
                 .constraints([
-                    Constraint::Percentage(25),
-                    Constraint::Percentage(25),
-                    Constraint::Percentage(50),
+                    Constraint::Length(3),
+                    Constraint::Length(describe_height),
+                    Constraint::Min(3),
+                    Constraint::Length(typing_height),
                 ])
-                .margin(1)
+                .margin(margin)
                 .split(chunks[0]);
+            let title_chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Min(1), Constraint::Length(LANGUAGE_WIDTH)])
+                .split(detail_chunks[0]);
+            let areas = [title_chunks[0], title_chunks[1], detail_chunks[1], detail_chunks[2], detail_chunks[3]];
 
-            // 创建标题区块
-            let title_block = Block::default()
-                .borders(Borders::ALL)
-                .title("标题");
-            let title_paragraph = Paragraph::new(
-                if (app.in_edit_mode || app.in_save_mode) && app.current_detail_selection == DetailSelection::Title {
-                    app.edit_buffer.clone()
-                } else {
-                    current_item.title.clone()
-                }
-            )
-            .block(title_block)
-            .style(
-                if app.current_detail_selection == DetailSelection::Title {
-                    if app.in_edit_mode {
-                        Style::default().fg(Color::White).bg(Color::DarkGray)
-                    } else {
-                        Style::default().fg(Color::White).bg(Color::Blue)
-                    }
+            // 高亮结果由后台线程计算，绘制时只读取缓存；语言没有设置时显示自动识别的结果
+            let location = (app.current_subscription, app.current_detail_index);
+            let highlighted = app.highlighter.get(location);
+            let highlight_lines = highlighted.map(|h| h.lines.as_slice()).unwrap_or(&[]);
+            let syntax = highlighted.and_then(|h| h.syntax.as_deref());
+            let language = current_item.language.as_deref().unwrap_or("(自动识别)");
+
+            let typing_text = current_item.typing.to_text(app.typing_settings);
+            let preview = app.current_preview().filter(|_| view == AppState::DetailPage);
+            let fields = [
+                (DetailSelection::Title, "标题", current_item.title.as_str()),
+                (DetailSelection::Language, "语言", language),
+                (DetailSelection::Describe, "描述", current_item.describe.as_str()),
+                match preview {
+                    Some(text) => (DetailSelection::Text, "文本 (预览)", text),
+                    None => (DetailSelection::Text, "文本", current_item.text.as_str()),
+                },
+                (DetailSelection::Typing, "输出设置", typing_text.as_str()),
+            ];
+
+            // 渲染各个区块，编辑中的区块显示编辑器内容
+            for ((selection, title, content), area) in fields.into_iter().zip(areas.iter()) {
+                let selected = app.current_detail_selection == selection;
+                let content = if editing && selected {
+                    app.editor.text()
                 } else {
+                    content.to_string()
+                };
+                let style = if !selected {
                     Style::default().fg(Color::White)
-                }
-            );
-
-            // 创建描述区块
-            let describe_block = Block::default()
-                .borders(Borders::ALL)
-                .title("描述");
-            let describe_paragraph = Paragraph::new(
-                if (app.in_edit_mode || app.in_save_mode) && app.current_detail_selection == DetailSelection::Describe {
-                    app.edit_buffer.clone()
+                } else if view == AppState::EditMode {
+                    Style::default().fg(Color::White).bg(Color::DarkGray)
                 } else {
-                    current_item.describe.clone()
+                    Style::default().fg(Color::White).bg(Color::Blue)
+                };
+
+                let mut title = vec![Span::raw(title)];
+                if selection == DetailSelection::Text
+                    && preview.is_none()
+                    && let Some(e) = &text_error
+                {
+                    title.push(Span::styled(format!(" ({})", e), Style::default().fg(Color::Red)));
                 }
-            )
-            .block(describe_block)
-            .style(
-                if app.current_detail_selection == DetailSelection::Describe {
-                    if app.in_edit_mode {
-                        Style::default().fg(Color::White).bg(Color::DarkGray)
-                    } else {
-                        Style::default().fg(Color::White).bg(Color::Blue)
-                    }
-                } else {
-                    Style::default().fg(Color::White)
+                if selection == DetailSelection::Language && highlighted.is_some() {
+                    title.push(match syntax {
+                        Some(name) => Span::raw(format!(" ({})", name)),
+                        None if current_item.language.is_some() => {
+                            Span::styled(" (不支持)", Style::default().fg(Color::Red))
+                        }
+                        None => Span::raw(" (纯文本)"),
+                    });
                 }
-            );
-
-            // 创建文本区块
-            let text_block = Block::default()
-                .borders(Borders::ALL)
-                .title("文本");
-            let text_paragraph = Paragraph::new(
-                if (app.in_edit_mode || app.in_save_mode) && app.current_detail_selection == DetailSelection::Text {
-                    app.edit_buffer.clone()
+
+                let block = Block::default().borders(Borders::ALL).title(Line::from(title));
+                if selection == DetailSelection::Text {
+                    let block = block.style(style);
+                    let cursor = (editing && selected).then(|| app.editor.cursor());
+                    text_cursor = draw_text_pane(f, &mut text_view, *area, block, &content, highlight_lines, cursor);
                 } else {
-                    current_item.text.clone()
+                    f.render_widget(Paragraph::new(content).block(block).style(style), *area);
                 }
-            )
-            .block(text_block)
-            .style(
-                if app.current_detail_selection == DetailSelection::Text {
-                    if app.in_edit_mode {
-                        Style::default().fg(Color::White).bg(Color::DarkGray)
-                    } else {
-                        Style::default().fg(Color::White).bg(Color::Blue)
-                    }
-                } else {
-                    Style::default().fg(Color::White)
+            }
+
+            // 编辑模式下把终端光标放到编辑位置，文本区块的光标位置由区块自己计算
+            if app.app_state == AppState::EditMode && app.current_detail_selection == DetailSelection::Text {
+                if let Some((x, y)) = text_cursor {
+                    f.set_cursor(x, y);
+                }
+            } else if app.app_state == AppState::EditMode {
+                let area = match app.current_detail_selection {
+                    DetailSelection::Title => areas[0],
+                    DetailSelection::Language => areas[1],
+                    DetailSelection::Describe => areas[2],
+                    DetailSelection::Text => areas[3],
+                    DetailSelection::Typing => areas[4],
+                };
+                let (row, _) = app.editor.cursor();
+                let x = area.x as usize + 1 + app.editor.cursor_x();
+                let y = area.y as usize + 1 + row;
+                // 光标超出区块范围时不显示
+                if x < area.right().saturating_sub(1) as usize && y < area.bottom().saturating_sub(1) as usize {
+                    f.set_cursor(x as u16, y as u16);
                 }
-            );
-                
-            // 渲染三个区块
-            f.render_widget(title_paragraph, detail_chunks[0]);
-            f.render_widget(describe_paragraph, detail_chunks[1]);
-            f.render_widget(text_paragraph, detail_chunks[2]);
+            }
         }
-    } else if app.in_detail_view {
-        // 显示详细内容列表
-        let items: Vec<ListItem> = app
-            .detail_items
+        app.text_view = text_view;
+    } else if view == AppState::DetailView {
+        // 显示当前订阅的详情项列表
+        let subscription = app.items.get(app.current_subscription);
+        let title = subscription.map(|s| s.name.as_str()).unwrap_or("items");
+        let details = subscription.map(|s| s.details.as_slice()).unwrap_or(&[]);
+        let items: Vec<ListItem> = details
             .iter()
             .map(|item| {
                 let line = Line::from(vec![
@@ -128,7 +204,7 @@ pub fn draw(f: &mut Frame, app: &mut App) {
 
         // 创建列表组件
         let items = List::new(items)
-            .block(Block::default().borders(Borders::ALL).title("items"))
+            .block(Block::default().borders(Borders::ALL).title(title))
             .highlight_style(
                 Style::default()
                     .bg(Color::LightBlue)
@@ -146,7 +222,7 @@ pub fn draw(f: &mut Frame, app: &mut App) {
             .items
             .iter()
             .map(|i| {
-                let line = Line::from(i.as_str());
+                let line = Line::from(i.name.as_str());
                 ListItem::new(line).style(Style::default().fg(Color::White))
             })
             .collect();
@@ -166,107 +242,553 @@ pub fn draw(f: &mut Frame, app: &mut App) {
         f.render_stateful_widget(items, chunks[0], &mut app.state);
     }
 
-    // 创建说明栏文本
-    let help_text = get_help_text(app);
-
     // 创建说明栏组件
-    let help_paragraph = Paragraph::new(help_text)
-        .block(Block::default().borders(Borders::ALL).title("操作说明"))
-        .style(Style::default().fg(Color::Gray));
+    let mut help_paragraph = Paragraph::new(footer_text(app, compact, size.width)).style(Style::default().fg(Color::Gray));
+    if !compact {
+        help_paragraph = help_paragraph.block(Block::default().borders(Borders::ALL).title(help_title(app)));
+    }
+
+    // 有输出任务时在说明栏上方显示倒计时或进度
+    let help_area = match &app.typing {
+        Some(job) => {
+            let help_chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(1), Constraint::Min(0)])
+                .split(chunks[1]);
+            f.render_widget(typing_gauge(app, job.phase()), help_chunks[0]);
+            help_chunks[1]
+        }
+        None => chunks[1],
+    };
 
     // 渲染说明栏
-    f.render_widget(help_paragraph, chunks[1]);
+    f.render_widget(help_paragraph, help_area);
+
+    if app.palette.is_some() {
+        draw_palette(f, app, chunks[0]);
+    }
+
+    if app.help.is_some() {
+        draw_help(f, app);
+    }
+
+    if let Some(message) = app.current_toast() {
+        draw_toast(f, message);
+    }
 }
 
-// 生成帮助文本
-fn get_help_text(app: &App) -> Vec<Line> {
-    if app.in_detail_page {
-        if app.in_save_mode {
-            vec![
-                Line::from(vec![
-                    Span::styled("选择保存方式:", Style::default().fg(Color::Green)),
-                    Span::raw("  "),
-                ]),
-                Line::from(vec![
-                    Span::styled("q", Style::default().fg(Color::Yellow)),
-                    Span::raw(" - 不保存退出  "),
-                    Span::styled("w", Style::default().fg(Color::Yellow)),
-                    Span::raw(" - 保存并退出"),
-                ]),
-                Line::from(vec![
-                    Span::styled("Esc", Style::default().fg(Color::Yellow)),
-                    Span::raw(" - 返回编辑模式"),
-                ]),
-            ]
-        } else if app.in_edit_mode {
-            vec![
-                Line::from(vec![
-                    Span::styled("Esc", Style::default().fg(Color::Yellow)),
-                    Span::raw(" - 完成编辑"),
-                ]),
-                Line::from(vec![
-                    Span::styled("字符输入", Style::default().fg(Color::Yellow)),
-                    Span::raw(" - 输入文本  "),
-                    Span::styled("Backspace", Style::default().fg(Color::Yellow)),
-                    Span::raw(" - 删除字符  "),
-                    Span::styled("Enter", Style::default().fg(Color::Yellow)),
-                    Span::raw(" - 换行"),
-                ]),
-            ]
-        } else {
-            vec![
-                Line::from(vec![
-                    Span::styled("↑/↓/j/k", Style::default().fg(Color::Yellow)),
-                    Span::raw(" - 切换选择项  "),
-                    Span::styled("v", Style::default().fg(Color::Yellow)),
-                    Span::raw(" - 编辑选中项"),
-                ]),
-                Line::from(vec![
-                    Span::styled("d", Style::default().fg(Color::Yellow)),
-                    Span::raw(" - 清空选中项内容  "),
-                    Span::styled("q/Esc", Style::default().fg(Color::Yellow)),
-                    Span::raw(" - 返回items列表  "),
-                    Span::styled("b", Style::default().fg(Color::Yellow)),
-                    Span::raw(" - 模拟键盘输出(2秒后)"),
-                ]),
-            ]
+/// 详情项的文本区块：可以上下左右滚动，可选自动换行和行号，内容超出区块时显示滚动条
+///
+/// 编辑时传入编辑器的光标，滚动位置跟随光标，返回光标在屏幕上的位置。
+/// highlight 是缓存的高亮结果，与当前内容不一致的行按普通文本显示。
+fn draw_text_pane(
+    f: &mut Frame,
+    view: &mut TextView,
+    area: Rect,
+    block: Block,
+    content: &str,
+    highlight: &[StyledLine],
+    cursor: Option<(usize, usize)>,
+) -> Option<(u16, u16)> {
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let lines: Vec<&str> = content.split('\n').collect();
+    let gutter = if view.line_numbers { lines.len().to_string().len() + 1 } else { 0 };
+    let width = (inner.width as usize).saturating_sub(gutter).max(1);
+    let height = inner.height as usize;
+    let layout = TextLayout::new(&lines, width, view.wrap);
+    let cursor = cursor.map(|cursor| layout.cursor_position(&lines, cursor));
+    view.fit(&layout, width, height, cursor);
+
+    let number_style = Style::default().fg(Color::DarkGray);
+    let text: Vec<Line> = layout
+        .rows
+        .iter()
+        .skip(view.scroll)
+        .take(height)
+        .map(|row| {
+            let mut spans = Vec::new();
+            // 折行后的后续行不重复显示行号
+            if gutter > 0 {
+                let number = if row.start == 0 { (row.line + 1).to_string() } else { String::new() };
+                spans.push(Span::styled(format!("{:>width$} ", number, width = gutter - 1), number_style));
+            }
+            let line = lines[row.line];
+            let (lead, range, trail) = if view.wrap {
+                (0, row.start..row.end, 0)
+            } else {
+                column_range(line, view.hscroll, width)
+            };
+            spans.push(Span::raw(" ".repeat(lead)));
+            match highlight.get(row.line).filter(|styled| same_text(styled, line)) {
+                Some(styled) => spans.extend(
+                    slice_styled(styled, range)
+                        .into_iter()
+                        .map(|(color, text)| Span::styled(text, Style::default().fg(color))),
+                ),
+                None => spans.push(Span::raw(line.chars().skip(range.start).take(range.len()).collect::<String>())),
+            }
+            spans.push(Span::raw(" ".repeat(trail)));
+            Line::from(spans)
+        })
+        .collect();
+    f.render_widget(Paragraph::new(text), inner);
+
+    // 滚动条画在边框上，滑块到达末端时内容的最后一行正好显示在区块底部
+    if layout.rows.len() > height {
+        let mut state = ScrollbarState::new(layout.rows.len() - height + 1)
+            .viewport_content_length(height)
+            .position(view.scroll);
+        let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight).begin_symbol(None).end_symbol(None);
+        f.render_stateful_widget(scrollbar, area.inner(&Margin { vertical: 1, horizontal: 0 }), &mut state);
+    }
+    let content_width = layout.max_width + usize::from(cursor.is_some());
+    if !view.wrap && content_width > width {
+        let mut state = ScrollbarState::new(content_width - width + 1)
+            .viewport_content_length(width)
+            .position(view.hscroll);
+        let scrollbar = Scrollbar::new(ScrollbarOrientation::HorizontalBottom).begin_symbol(None).end_symbol(None);
+        let track = Rect { x: inner.x + gutter as u16, width: width as u16, ..area };
+        f.render_stateful_widget(scrollbar, track, &mut state);
+    }
+
+    let (row, x) = cursor?;
+    let x = x.checked_sub(view.hscroll)?;
+    let y = row.checked_sub(view.scroll)?;
+    (x < width && y < height).then(|| (inner.x + (gutter + x) as u16, inner.y + y as u16))
+}
+
+/// 说明栏的内容，有状态消息时显示在最前面，收起时只有一行
+fn footer_text(app: &App, compact: bool, width: u16) -> Vec<Line<'_>> {
+    if compact {
+        return vec![help_hint(app)];
+    }
+    let mut lines = Vec::new();
+    if let Some(message) = &app.status_message {
+        lines.push(Line::from(Span::styled(message.as_str(), Style::default().fg(Color::Red))));
+    }
+    // 去掉左右边框占用的宽度
+    lines.extend(get_help_text(app, width.saturating_sub(2) as usize));
+    lines
+}
+
+/// 终端太小时代替整个界面显示的提示
+fn draw_too_small(f: &mut Frame) {
+    let size = f.size();
+    let text = vec![
+        Line::from("终端窗口太小"),
+        Line::from(format!(
+            "当前 {}x{}，至少需要 {}x{}",
+            size.width, size.height, MIN_WIDTH, MIN_HEIGHT
+        )),
+    ];
+    let paragraph = Paragraph::new(text)
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true })
+        .style(Style::default().fg(Color::Yellow));
+    let area = Rect::new(size.x, size.y + size.height.saturating_sub(2) / 2, size.width, size.height.min(2));
+    f.render_widget(paragraph, area);
+}
+
+/// 命令面板：覆盖在当前视图中间，上方为过滤输入框，下方为动作和按键
+fn draw_palette(f: &mut Frame, app: &mut App, area: Rect) {
+    let Some(palette) = &mut app.palette else {
+        return;
+    };
+    let width = area.width.saturating_sub(4).clamp(area.width.min(20), 60);
+    let height = (palette.entries.len() as u16 + 5).clamp(area.height.min(6), area.height);
+    let popup = Rect::new(area.x + (area.width - width) / 2, area.y + (area.height - height) / 2, width, height);
+    f.render_widget(Clear, popup);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(0)])
+        .split(popup);
+    let input = Paragraph::new(palette.editor.text())
+        .block(Block::default().borders(Borders::ALL).title("命令面板"))
+        .style(Style::default().fg(Color::White));
+    f.render_widget(input, chunks[0]);
+
+    let key_style = Style::default().fg(Color::Yellow);
+    let matched = Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD);
+    let items: Vec<ListItem> = palette
+        .entries
+        .iter()
+        .map(|entry| {
+            let mut spans: Vec<Span> = entry
+                .description
+                .chars()
+                .enumerate()
+                .map(|(i, c)| {
+                    let style = if entry.indices.contains(&i) { matched } else { Style::default() };
+                    Span::styled(c.to_string(), style)
+                })
+                .collect();
+            spans.push(Span::raw("  "));
+            spans.push(Span::styled(join_keys(&entry.keys), key_style));
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL))
+        .highlight_style(Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD))
+        .highlight_symbol(">> ");
+    f.render_stateful_widget(list, chunks[1], &mut palette.list);
+
+    let x = chunks[0].x as usize + 1 + palette.editor.cursor_x();
+    if x < chunks[0].right().saturating_sub(1) as usize {
+        f.set_cursor(x as u16, chunks[0].y + 1);
+    }
+}
+
+/// 在右上角显示提示消息，覆盖在其他内容之上
+fn draw_toast(f: &mut Frame, message: &str) {
+    let screen = f.size();
+    let width = (display_width(message.chars()) as u16 + 4).min(screen.width);
+    let height = 3.min(screen.height);
+    let area = Rect::new(screen.right() - width, screen.y, width, height);
+
+    let paragraph = Paragraph::new(message)
+        .block(Block::default().borders(Borders::ALL))
+        .style(Style::default().fg(Color::Black).bg(Color::Green));
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
+}
+
+/// 输出任务的进度条：倒计时阶段显示剩余秒数，输出阶段显示完成百分比
+fn typing_gauge(app: &App, phase: TypingPhase) -> Gauge<'static> {
+    let mut cancel_keys = app
+        .keymap
+        .bindings(app.app_state)
+        .iter()
+        .find(|b| b.event == InputEvent::CancelOutput)
+        .and_then(|b| b.keys.first())
+        .map(|k| format!("，按 {} 取消", k))
+        .unwrap_or_default();
+    // 只有 Windows 能在其他窗口中检测停止热键，其他平台需要回到本程序取消
+    if cfg!(windows) {
+        cancel_keys.push_str("，任意窗口按 Esc 停止");
+    } else if !cancel_keys.is_empty() {
+        cancel_keys.push_str("（需回到本程序）");
+    }
+
+    let (ratio, label, color) = match phase {
+        TypingPhase::Countdown(remaining) => (
+            0.0,
+            format!("{:.1} 秒后开始输出{}", remaining.as_secs_f64(), cancel_keys),
+            Color::Yellow,
+        ),
+        TypingPhase::Typing { done, total } => {
+            let ratio = if total == 0 { 1.0 } else { done as f64 / total as f64 };
+            (
+                ratio,
+                format!("正在输出 {}% ({}/{}){}", (ratio * 100.0) as u32, done, total, cancel_keys),
+                Color::Green,
+            )
         }
-    } else if app.in_detail_view {
-        vec![
+    };
+
+    Gauge::default()
+        .gauge_style(Style::default().fg(color).bg(Color::Black))
+        .ratio(ratio)
+        .label(label)
+}
+
+/// 填写模板变量的表单，每行一个变量，光标放在选中的变量上
+fn draw_prompt(f: &mut Frame, app: &App, area: Rect) {
+    let Some(form) = &app.prompt else {
+        return;
+    };
+    let title = app
+        .items
+        .get(form.subscription)
+        .and_then(|s| s.details.get(form.detail))
+        .map(|item| format!("填写变量 - {}", item.title))
+        .unwrap_or_else(|| "填写变量".to_string());
+
+    let label_width = form
+        .variables
+        .iter()
+        .map(|v| display_width(v.name.chars()))
+        .max()
+        .unwrap_or(0)
+        + 2;
+    let lines: Vec<Line> = form
+        .variables
+        .iter()
+        .enumerate()
+        .map(|(i, variable)| {
+            let padding = label_width - display_width(variable.name.chars());
+            let style = if i == form.selected() {
+                Style::default().fg(Color::White).bg(Color::DarkGray)
+            } else {
+                Style::default().fg(Color::White)
+            };
             Line::from(vec![
-                Span::styled("q/Esc", Style::default().fg(Color::Yellow)),
-                Span::raw(" - 返回主列表  "),
-                Span::styled("↑/↓/j/k", Style::default().fg(Color::Yellow)),
-                Span::raw(" - 上下导航  "),
-                Span::styled("Enter", Style::default().fg(Color::Yellow)),
-                Span::raw(" - 查看项目详情"),
-            ]),
-        ]
+                Span::styled(variable.name.clone(), Style::default().fg(Color::Yellow)),
+                Span::raw(" ".repeat(padding)),
+                Span::styled(form.value(i), style),
+            ])
+        })
+        .collect();
+
+    let paragraph = Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title));
+    f.render_widget(paragraph, area);
+
+    // 光标放到选中变量的编辑位置，超出区块范围时不显示
+    let x = area.x as usize + 1 + label_width + form.editor.cursor_x();
+    let y = area.y as usize + 1 + form.selected();
+    if x < area.right().saturating_sub(1) as usize && y < area.bottom().saturating_sub(1) as usize {
+        f.set_cursor(x as u16, y as u16);
+    }
+}
+
+/// 搜索界面：上方为关键字输入框，下方为按匹配程度排列的结果
+fn draw_search(f: &mut Frame, app: &mut App, area: Rect) {
+    let Some(search) = &mut app.search else {
+        return;
+    };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(0)])
+        .split(area);
+
+    let input = Paragraph::new(search.editor.text())
+        .block(Block::default().borders(Borders::ALL).title("搜索"))
+        .style(Style::default().fg(Color::White));
+    f.render_widget(input, chunks[0]);
+
+    let items: Vec<ListItem> = search
+        .hits
+        .iter()
+        .map(|hit| ListItem::new(search_hit_line(&app.items, hit)))
+        .collect();
+    let title = format!("结果 ({})", search.hits.len());
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD))
+        .highlight_symbol(">> ");
+    f.render_stateful_widget(list, chunks[1], &mut search.list);
+
+    let x = chunks[0].x as usize + 1 + search.editor.cursor_x();
+    if x < chunks[0].right().saturating_sub(1) as usize {
+        f.set_cursor(x as u16, chunks[0].y + 1);
+    }
+}
+
+/// 一条搜索结果：所在位置，以及高亮了匹配字符的那一行文本
+fn search_hit_line<'a>(items: &'a [Subscription], hit: &'a SearchHit) -> Line<'a> {
+    let subscription = &items[hit.subscription];
+    let mut spans = Vec::new();
+    if let Some(detail) = hit.detail {
+        let field = match hit.field {
+            SearchField::Describe => "描述",
+            SearchField::Text => "文本",
+            _ => "标题",
+        };
+        let title = &subscription.details[detail].title;
+        spans.push(Span::styled(
+            format!("[{} / {}] {}: ", subscription.name, title, field),
+            Style::default().fg(Color::Cyan),
+        ));
     } else {
-        vec![
-            Line::from(vec![
-                Span::styled("q/Esc", Style::default().fg(Color::Yellow)),
-                Span::raw(" - 退出程序  "),
-                Span::styled("↑/↓/j/k", Style::default().fg(Color::Yellow)),
-                Span::raw(" - 上下导航  "),
-                Span::styled("Enter", Style::default().fg(Color::Yellow)),
-                Span::raw(" - 选择项目"),
-                Span::raw(" - 刷新列表  "),
-                Span::styled("t", Style::default().fg(Color::Yellow)),
+        spans.push(Span::styled("[订阅] ", Style::default().fg(Color::Cyan)));
+    }
+
+    let matched = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+    for (i, c) in hit.line.chars().enumerate() {
+        let style = if hit.indices.contains(&i) { matched } else { Style::default().fg(Color::White) };
+        spans.push(Span::styled(c.to_string(), style));
+    }
+    Line::from(spans)
+}
+
+/// 全屏帮助，内容可以上下滚动
+fn draw_help(f: &mut Frame, app: &mut App) {
+    let lines = help_lines(app);
+    let close_keys = app
+        .keymap
+        .bindings(AppState::HelpMode)
+        .iter()
+        .find(|b| b.event == InputEvent::Escape)
+        .map(|b| join_keys(&b.keys))
+        .unwrap_or_default();
+    let Some(help) = &mut app.help else {
+        return;
+    };
+
+    let area = f.size();
+    let visible = area.height.saturating_sub(2) as usize;
+    help.page = visible.max(1);
+    help.scroll = help.scroll.min(lines.len().saturating_sub(visible));
+
+    let title = format!("帮助 ({}/{}，按 {} 关闭)", help.scroll + 1, lines.len(), close_keys);
+    let paragraph = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .style(Style::default().fg(Color::Gray))
+        .scroll((help.scroll as u16, 0));
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
+}
+
+/// 全屏帮助的内容：按视图分组列出每个动作及其按键
+///
+/// 与输入处理使用同一份按键绑定生成，配置文件修改的按键也会显示在这里。
+fn help_lines(app: &App) -> Vec<Line<'static>> {
+    let key_style = Style::default().fg(Color::Yellow);
+    let mut lines = Vec::new();
+
+    for state in VIEWS {
+        let mut entries: Vec<(String, &str)> = app
+            .keymap
+            .bindings(state)
+            .iter()
+            .map(|b| (join_keys(&b.keys), action_description(state, b.event)))
+            .collect();
+        if accepts_text(state) {
+            entries.push(("字符输入".to_string(), action_description(state, InputEvent::CharacterInput(' '))));
+        }
+        let key_width = entries.iter().map(|(keys, _)| display_width(keys.chars())).max().unwrap_or(0);
+
+        lines.push(Line::from(vec![
+            Span::styled(view_title(state), Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
+            Span::styled(format!(" ({})", view_name(state)), Style::default().fg(Color::DarkGray)),
+        ]));
+        for (keys, description) in entries {
+            let padding = key_width - display_width(keys.chars()) + 2;
+            lines.push(Line::from(vec![
+                Span::raw("  "),
+                Span::styled(keys, key_style),
+                Span::raw(" ".repeat(padding)),
+                Span::raw(description),
+            ]));
+        }
+        lines.push(Line::default());
+    }
+    lines.pop();
+    lines
+}
+
+/// 绑定的按键，多个按键用斜杠分隔
+fn join_keys(keys: &[KeyChord]) -> String {
+    if keys.is_empty() {
+        return "未绑定".to_string();
+    }
+    keys.iter().map(|k| k.to_string()).collect::<Vec<_>>().join("/")
+}
+
+/// 说明栏标题，附带可撤销/重做的步数
+fn help_title(app: &App) -> String {
+    format!("操作说明 ({})", undo_summary(app))
+}
+
+/// 可撤销和可重做的步数，编辑模式中是编辑器自己的历史
+fn undo_summary(app: &App) -> String {
+    let (undo, redo) = if app.app_state == AppState::EditMode {
+        (app.editor.undo_depth(), app.editor.redo_depth())
+    } else {
+        (app.history.undo_depth(), app.history.redo_depth())
+    };
+    format!("撤销 {} / 重做 {}", undo, redo)
+}
+
+/// 同一行中相邻两条按键说明之间的空格
+const HELP_ENTRY_GAP: &str = "  ";
+
+/// 当前视图每个动作的按键说明
+fn help_entries(app: &App) -> Vec<(InputEvent, Vec<Span<'_>>)> {
+    let key_style = Style::default().fg(Color::Yellow);
+    let mut entries = Vec::new();
+
+    for binding in app.keymap.bindings(app.app_state) {
+        if binding.keys.is_empty() {
+            continue;
+        }
+
+        let mut spans = vec![Span::styled(join_keys(&binding.keys), key_style), Span::raw(" - ")];
+        if binding.event == InputEvent::TogglePinned {
+            spans.push(Span::styled(
+                if app.is_pinned { "取消置顶" } else { "窗口置顶" },
+                Style::default().fg(if app.is_pinned { Color::Red } else { Color::Green }),
+            ));
+        } else {
+            spans.push(Span::raw(action_description(app.app_state, binding.event)));
+        }
+        entries.push((binding.event, spans));
+    }
+
+    if accepts_text(app.app_state) {
+        let event = InputEvent::CharacterInput(' ');
+        entries.push((
+            event,
+            vec![
+                Span::styled("字符输入", key_style),
                 Span::raw(" - "),
-                Span::styled(
-                    if app.is_pinned { "取消置顶" } else { "窗口置顶" },
-                    Style::default().fg(if app.is_pinned { Color::Red } else { Color::Green })
-                ),
-            ]),
-            Line::from(vec![
-                Span::styled("a", Style::default().fg(Color::Yellow)),
-                Span::raw(" - 添加订阅  "),
-                Span::styled("d", Style::default().fg(Color::Yellow)),
-                Span::raw(" - 删除订阅  "),
-                Span::styled("r", Style::default().fg(Color::Yellow)),
-                Span::raw(" - 刷新列表"),
-            ]),
-        ]
+                Span::raw(action_description(app.app_state, event)),
+            ],
+        ));
+    }
+    entries
+}
+
+// 根据当前视图的按键绑定生成帮助文本，按说明栏的宽度把按键说明依次排成若干行
+fn get_help_text(app: &App, width: usize) -> Vec<Line<'_>> {
+    let mut lines = Vec::new();
+    if app.app_state == AppState::SaveMode {
+        lines.push(Line::from(Span::styled("选择保存方式:", Style::default().fg(Color::Green))));
+    }
+
+    let mut row: Vec<&Vec<Span>> = Vec::new();
+    let mut row_width = 0;
+    let entries = help_entries(app);
+    for (_, entry) in &entries {
+        let entry_width: usize = entry.iter().map(|span| display_width(span.content.chars())).sum();
+        // 放不下时换行，一条说明比整行还宽时单独占一行
+        if !row.is_empty() && row_width + HELP_ENTRY_GAP.len() + entry_width > width {
+            lines.push(join_entries(row.drain(..)));
+        }
+        row_width = if row.is_empty() {
+            entry_width
+        } else {
+            row_width + HELP_ENTRY_GAP.len() + entry_width
+        };
+        row.push(entry);
+    }
+    if !row.is_empty() {
+        lines.push(join_entries(row.into_iter()));
     }
-}
\ No newline at end of file
+    lines
+}
+
+/// 收起的说明栏：有状态消息时只显示消息，否则把全屏帮助和命令面板的按键放在最前面，
+/// 其余按键说明排在同一行，超出宽度的部分不显示
+///
+/// 收起时没有说明栏标题，可以撤销的视图在行首显示撤销和重做的步数。
+fn help_hint(app: &App) -> Line<'_> {
+    let mut spans = Vec::new();
+    if app.keymap.bindings(app.app_state).iter().any(|b| b.event == InputEvent::Undo) {
+        spans.push(Span::styled(undo_summary(app), Style::default().fg(Color::DarkGray)));
+        spans.push(Span::raw(HELP_ENTRY_GAP));
+    }
+    if let Some(message) = &app.status_message {
+        spans.push(Span::styled(message.as_str(), Style::default().fg(Color::Red)));
+        return Line::from(spans);
+    }
+    let mut entries = help_entries(app);
+    entries.sort_by_key(|(event, _)| !matches!(event, InputEvent::ShowHelp | InputEvent::Help));
+    spans.extend(join_entries(entries.iter().map(|(_, spans)| spans)).spans);
+    Line::from(spans)
+}
+
+/// 把几条按键说明排成一行，中间用空格分隔
+fn join_entries<'a, 'b: 'a>(entries: impl Iterator<Item = &'a Vec<Span<'b>>>) -> Line<'b> {
+    let mut spans = Vec::new();
+    for (i, entry) in entries.enumerate() {
+        if i > 0 {
+            spans.push(Span::raw(HELP_ENTRY_GAP));
+        }
+        spans.extend(entry.iter().cloned());
+    }
+    Line::from(spans)
+}
@@ -0,0 +1,337 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::mem;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+use std::slice;
+use std::time::Duration;
+
+use crate::output::{Key, KeyAction, Modifiers};
+
+const UINPUT_PATH: &str = "/dev/uinput";
+const DEVICE_NAME: &[u8] = b"heillo virtual keyboard";
+/// 创建设备后等待桌面环境识别新键盘的时间，太早发送的按键会丢失
+pub const SETTLE_DELAY: Duration = Duration::from_millis(200);
+
+// linux/input-event-codes.h
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const SYN_REPORT: u16 = 0;
+const BUS_VIRTUAL: u16 = 0x06;
+
+const KEY_ESC: u16 = 1;
+const KEY_BACKSPACE: u16 = 14;
+const KEY_TAB: u16 = 15;
+const KEY_ENTER: u16 = 28;
+const KEY_LEFTCTRL: u16 = 29;
+const KEY_LEFTSHIFT: u16 = 42;
+const KEY_LEFTALT: u16 = 56;
+const KEY_SPACE: u16 = 57;
+const KEY_F1: u16 = 59;
+const KEY_F11: u16 = 87;
+const KEY_HOME: u16 = 102;
+const KEY_UP: u16 = 103;
+const KEY_PAGEUP: u16 = 104;
+const KEY_LEFT: u16 = 105;
+const KEY_RIGHT: u16 = 106;
+const KEY_END: u16 = 107;
+const KEY_DOWN: u16 = 108;
+const KEY_PAGEDOWN: u16 = 109;
+const KEY_INSERT: u16 = 110;
+const KEY_DELETE: u16 = 111;
+const KEY_LEFTMETA: u16 = 125;
+
+/// 文本中不会出现、只能通过标记按下的按键
+const EXTRA_KEYS: &[u16] = &[
+    KEY_ESC, KEY_BACKSPACE, KEY_LEFTCTRL, KEY_LEFTSHIFT, KEY_LEFTALT, KEY_LEFTMETA,
+    KEY_HOME, KEY_UP, KEY_PAGEUP, KEY_LEFT, KEY_RIGHT, KEY_END, KEY_DOWN, KEY_PAGEDOWN,
+    KEY_INSERT, KEY_DELETE,
+];
+
+// linux/uinput.h 中的 ioctl 请求号
+const UI_DEV_CREATE: u64 = io_none(1);
+const UI_DEV_DESTROY: u64 = io_none(2);
+const UI_DEV_SETUP: u64 = io_write(3, mem::size_of::<libc::uinput_setup>());
+const UI_SET_EVBIT: u64 = io_write(100, mem::size_of::<libc::c_int>());
+const UI_SET_KEYBIT: u64 = io_write(101, mem::size_of::<libc::c_int>());
+
+const fn io_none(nr: u64) -> u64 {
+    (b'U' as u64) << 8 | nr
+}
+
+const fn io_write(nr: u64, size: usize) -> u64 {
+    1 << 30 | (size as u64) << 16 | (b'U' as u64) << 8 | nr
+}
+
+/// 美式键盘布局下不需要 Shift 的字符及其键码
+const PLAIN_KEYS: &[(char, u16)] = &[
+    ('1', 2), ('2', 3), ('3', 4), ('4', 5), ('5', 6),
+    ('6', 7), ('7', 8), ('8', 9), ('9', 10), ('0', 11),
+    ('-', 12), ('=', 13),
+    ('q', 16), ('w', 17), ('e', 18), ('r', 19), ('t', 20),
+    ('y', 21), ('u', 22), ('i', 23), ('o', 24), ('p', 25),
+    ('[', 26), (']', 27),
+    ('a', 30), ('s', 31), ('d', 32), ('f', 33), ('g', 34),
+    ('h', 35), ('j', 36), ('k', 37), ('l', 38),
+    (';', 39), ('\'', 40), ('`', 41), ('\\', 43),
+    ('z', 44), ('x', 45), ('c', 46), ('v', 47), ('b', 48),
+    ('n', 49), ('m', 50),
+    (',', 51), ('.', 52), ('/', 53),
+    (' ', KEY_SPACE), ('\n', KEY_ENTER), ('\t', KEY_TAB),
+];
+
+/// 美式键盘布局下需要 Shift 的符号及其对应的未按 Shift 的字符
+const SHIFTED_SYMBOLS: &[(char, char)] = &[
+    ('!', '1'), ('@', '2'), ('#', '3'), ('$', '4'), ('%', '5'),
+    ('^', '6'), ('&', '7'), ('*', '8'), ('(', '9'), (')', '0'),
+    ('_', '-'), ('+', '='), ('{', '['), ('}', ']'), (':', ';'),
+    ('"', '\''), ('~', '`'), ('|', '\\'), ('<', ','), ('>', '.'),
+    ('?', '/'),
+];
+
+/// 把字符映射为键码和是否需要按住 Shift
+///
+/// uinput 发送的是物理键码，由桌面环境按当前键盘布局翻译成字符。这里假定布局是美式键盘，
+/// 其他布局下符号（德语布局下甚至 y 和 z）会输入成别的字符；中文等键盘上没有的字符返回错误。
+fn key_for_char(c: char) -> io::Result<(u16, bool)> {
+    let plain = |c: char| PLAIN_KEYS.iter().find(|(k, _)| *k == c).map(|(_, code)| *code);
+
+    let key = if c.is_ascii_uppercase() {
+        plain(c.to_ascii_lowercase()).map(|code| (code, true))
+    } else if let Some((_, base)) = SHIFTED_SYMBOLS.iter().find(|(k, _)| *k == c) {
+        plain(*base).map(|code| (code, true))
+    } else {
+        plain(c).map(|code| (code, false))
+    };
+    key.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("uinput 只能输入美式键盘上的字符，无法输入 {:?}", c),
+        )
+    })
+}
+
+/// 把标记中的按键映射为键码和是否需要按住 Shift
+fn key_code(key: Key) -> io::Result<(u16, bool)> {
+    let code = match key {
+        Key::Char(c) => return key_for_char(c),
+        Key::Enter => KEY_ENTER,
+        Key::Tab => KEY_TAB,
+        Key::Escape => KEY_ESC,
+        Key::Backspace => KEY_BACKSPACE,
+        Key::Delete => KEY_DELETE,
+        Key::Insert => KEY_INSERT,
+        Key::Space => KEY_SPACE,
+        Key::Up => KEY_UP,
+        Key::Down => KEY_DOWN,
+        Key::Left => KEY_LEFT,
+        Key::Right => KEY_RIGHT,
+        Key::Home => KEY_HOME,
+        Key::End => KEY_END,
+        Key::PageUp => KEY_PAGEUP,
+        Key::PageDown => KEY_PAGEDOWN,
+        // F1~F10 的键码连续，F11、F12 在后面
+        Key::F(n @ 1..=10) => KEY_F1 + n as u16 - 1,
+        Key::F(n @ 11..=12) => KEY_F11 + n as u16 - 11,
+        Key::F(n) => {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("uinput 不支持按键 F{}", n)));
+        }
+    };
+    Ok((code, false))
+}
+
+/// 检查全部按键动作都能通过虚拟键盘输入，返回第一个无法输入的错误
+pub fn check(actions: &[KeyAction]) -> io::Result<()> {
+    for action in actions {
+        match action {
+            KeyAction::Char(c) => key_for_char(*c).map(drop)?,
+            KeyAction::Key(key, _) => key_code(*key).map(drop)?,
+            KeyAction::Wait(_) => {}
+        }
+    }
+    Ok(())
+}
+
+/// uinput 虚拟键盘，销毁时自动移除设备
+pub struct VirtualKeyboard {
+    file: File,
+}
+
+impl VirtualKeyboard {
+    /// 打开 `/dev/uinput` 并注册虚拟键盘设备，等待 [`SETTLE_DELAY`] 后再发送按键
+    pub fn create() -> io::Result<VirtualKeyboard> {
+        let file = OpenOptions::new()
+            .write(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(UINPUT_PATH)
+            .map_err(explain_open_error)?;
+        let fd = file.as_raw_fd();
+
+        ioctl(fd, UI_SET_EVBIT, EV_KEY as libc::c_int)?;
+        let function_keys = (KEY_F1..KEY_F1 + 10).chain([KEY_F11, KEY_F11 + 1]);
+        let codes = PLAIN_KEYS.iter().map(|(_, code)| *code).chain(EXTRA_KEYS.iter().copied());
+        for code in codes.chain(function_keys) {
+            ioctl(fd, UI_SET_KEYBIT, code as libc::c_int)?;
+        }
+
+        // SAFETY: uinput_setup 是纯数据结构，全零是合法的初始值
+        let mut setup: libc::uinput_setup = unsafe { mem::zeroed() };
+        setup.id.bustype = BUS_VIRTUAL;
+        setup.id.vendor = 0x1209;
+        setup.id.product = 0x4e11;
+        for (dst, src) in setup.name.iter_mut().zip(DEVICE_NAME) {
+            *dst = *src as libc::c_char;
+        }
+        // SAFETY: fd 有效，setup 在调用期间一直存活
+        if unsafe { libc::ioctl(fd, UI_DEV_SETUP as _, &setup) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: fd 有效，UI_DEV_CREATE 不需要参数
+        if unsafe { libc::ioctl(fd, UI_DEV_CREATE as _) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(VirtualKeyboard { file })
+    }
+
+    /// 输入一个字符，无法在美式键盘布局下输入的字符返回错误
+    pub fn type_char(&mut self, c: char) -> io::Result<()> {
+        let (code, shift) = key_for_char(c)?;
+        self.chord(code, &if shift { vec![KEY_LEFTSHIFT] } else { Vec::new() })
+    }
+
+    /// 按下一个按键，同时按住修饰键，无法映射的按键返回错误
+    pub fn press(&mut self, key: Key, modifiers: Modifiers) -> io::Result<()> {
+        let (code, shift) = key_code(key)?;
+
+        let held: Vec<u16> = [
+            (modifiers.ctrl, KEY_LEFTCTRL),
+            (modifiers.alt, KEY_LEFTALT),
+            (modifiers.shift || shift, KEY_LEFTSHIFT),
+            (modifiers.win, KEY_LEFTMETA),
+        ]
+        .iter()
+        .filter(|(pressed, _)| *pressed)
+        .map(|(_, code)| *code)
+        .collect();
+        self.chord(code, &held)
+    }
+
+    /// 按住修饰键后按下并释放按键，再按相反顺序释放修饰键
+    fn chord(&mut self, code: u16, held: &[u16]) -> io::Result<()> {
+        for modifier in held {
+            self.key(*modifier, true)?;
+        }
+        self.key(code, true)?;
+        self.key(code, false)?;
+        for modifier in held.iter().rev() {
+            self.key(*modifier, false)?;
+        }
+        Ok(())
+    }
+
+    /// 发送一次按下或释放事件，并立即同步
+    fn key(&mut self, code: u16, pressed: bool) -> io::Result<()> {
+        self.emit(EV_KEY, code, pressed as i32)?;
+        self.emit(EV_SYN, SYN_REPORT, 0)
+    }
+
+    fn emit(&mut self, type_: u16, code: u16, value: i32) -> io::Result<()> {
+        // SAFETY: input_event 是纯数据结构，时间戳由内核填写
+        let mut event: libc::input_event = unsafe { mem::zeroed() };
+        event.type_ = type_;
+        event.code = code;
+        event.value = value;
+
+        // SAFETY: 按字节读取一个完整的 input_event
+        let bytes = unsafe {
+            slice::from_raw_parts(
+                &event as *const libc::input_event as *const u8,
+                mem::size_of::<libc::input_event>(),
+            )
+        };
+        self.file.write_all(bytes)
+    }
+}
+
+impl Drop for VirtualKeyboard {
+    fn drop(&mut self) {
+        // SAFETY: fd 在 file 销毁前一直有效
+        unsafe {
+            libc::ioctl(self.file.as_raw_fd(), UI_DEV_DESTROY as _);
+        }
+    }
+}
+
+fn ioctl(fd: libc::c_int, request: u64, value: libc::c_int) -> io::Result<()> {
+    // SAFETY: 这里使用的请求都只接受一个整数参数
+    if unsafe { libc::ioctl(fd, request as _, value) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// 给打开 `/dev/uinput` 时的常见错误加上处理建议
+fn explain_open_error(e: io::Error) -> io::Error {
+    let hint = match e.kind() {
+        io::ErrorKind::PermissionDenied => {
+            "没有权限打开 /dev/uinput，请把当前用户加入 input 组，\
+             或添加 udev 规则 KERNEL==\"uinput\", GROUP=\"input\", MODE=\"0660\""
+        }
+        io::ErrorKind::NotFound => "找不到 /dev/uinput，请先加载内核模块: sudo modprobe uinput",
+        _ => return e,
+    };
+    io::Error::new(e.kind(), hint)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_letters_and_digits() {
+        assert_eq!(key_for_char('a').unwrap(), (30, false));
+        assert_eq!(key_for_char('A').unwrap(), (30, true));
+        assert_eq!(key_for_char('z').unwrap(), (44, false));
+        assert_eq!(key_for_char('1').unwrap(), (2, false));
+        assert_eq!(key_for_char('\n').unwrap(), (KEY_ENTER, false));
+    }
+
+    #[test]
+    fn shifted_symbols_use_base_key() {
+        assert_eq!(key_for_char('!').unwrap(), key_for_char('1').map(|(code, _)| (code, true)).unwrap());
+        assert_eq!(key_for_char('{').unwrap(), (26, true));
+        assert_eq!(key_for_char('?').unwrap(), (53, true));
+        assert_eq!(key_for_char('"').unwrap(), (40, true));
+        assert_eq!(key_for_char('/').unwrap(), (53, false));
+    }
+
+    #[test]
+    fn unmappable_characters_are_errors() {
+        for c in ['中', 'é', '€', '\r'] {
+            let e = key_for_char(c).unwrap_err();
+            assert_eq!(e.kind(), io::ErrorKind::InvalidInput, "{:?}", c);
+        }
+        assert!(key_code(Key::Char('中')).is_err());
+        assert!(key_code(Key::F(13)).is_err());
+    }
+
+    #[test]
+    fn maps_named_keys() {
+        assert_eq!(key_code(Key::Escape).unwrap(), (KEY_ESC, false));
+        assert_eq!(key_code(Key::F(1)).unwrap(), (KEY_F1, false));
+        assert_eq!(key_code(Key::F(10)).unwrap(), (KEY_F1 + 9, false));
+        assert_eq!(key_code(Key::F(12)).unwrap(), (KEY_F11 + 1, false));
+        assert_eq!(key_code(Key::Char('T')).unwrap(), (20, true));
+    }
+
+    #[test]
+    fn check_rejects_the_whole_text_before_typing() {
+        let ok = [KeyAction::Char('l'), KeyAction::press(Key::Enter), KeyAction::Wait(Duration::from_millis(5))];
+        assert!(check(&ok).is_ok());
+
+        let mixed = [KeyAction::Char('l'), KeyAction::Char('s'), KeyAction::Char('中')];
+        assert!(check(&mixed).unwrap_err().to_string().contains("'中'"));
+        assert!(check(&[KeyAction::press(Key::F(20))]).is_err());
+    }
+}